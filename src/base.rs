@@ -1,327 +1,712 @@
-use crate::config::{CollectorConfig, Config, ExplorerConfig};
-use crate::generation::TypeCase;
+use crate::config::Config;
+use crate::events::Event;
+use crate::generation::{Deposits, Map, TypeCase};
+use crate::pathfinding::{neighbour, DistanceField, Pathfinder, NEIGHBOURS};
+use crate::production::{self, ProductionStrategy};
 use crate::robot::{Collector, Explorer, Robot};
-use std::cmp::Ordering;
-use std::collections::{BinaryHeap, HashSet};
-use std::sync::{Arc, Mutex};
-use std::thread;
-use std::time::Duration;
-
-// Sort resource by priority level
-#[derive(Clone, Debug, Eq)]
-struct PrioritizedResource {
-    x: usize,
-    y: usize,
-    distance: usize,
-    priority_level: usize,
-}
-
-// Implentation of necessary traits for the priority queue
-impl PartialEq for PrioritizedResource {
-    fn eq(&self, other: &Self) -> bool {
-        self.priority_level == other.priority_level && self.distance == other.distance
-    }
-}
-
-// Reverse the order so that the most prioritized and closest resources are at the beginning
-impl PartialOrd for PrioritizedResource {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
-impl Ord for PrioritizedResource {
-    fn cmp(&self, other: &Self) -> Ordering {
-        // Compare the priority level first
-        match self.priority_level.cmp(&other.priority_level) {
-            Ordering::Equal => {}
-            ordering => return ordering,
-        }
-
-        // Then compare the distance
-        other.distance.cmp(&self.distance)
-    }
-}
+use crate::sectors::{self, Sector};
+use crate::selection::{self, ResourceSelector};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
 
+#[derive(Serialize, Deserialize)]
 pub struct Base {
-    pub real_map: Arc<Mutex<Vec<Vec<TypeCase>>>>,
-    pub known_map: Arc<Mutex<Vec<Vec<TypeCase>>>>,
-    pub deployed_robots: Arc<Mutex<Vec<Box<dyn Robot + Send>>>>,
-    pub energy: Arc<Mutex<usize>>,
-    pub ore: Arc<Mutex<usize>>,
-    pub science: Arc<Mutex<usize>>,
-    pub position_x: usize,
-    pub position_y: usize,
-    reserved_resources: Arc<Mutex<HashSet<(usize, usize)>>>,
+    pub(crate) real_map: Map,
+    pub(crate) known_map: Map,
+    // Units left in each resource case of the real map
+    pub(crate) deposits: Deposits,
+    #[serde(with = "crate::robot::saved_robots")]
+    pub(crate) deployed_robots: Vec<Box<dyn Robot + Send>>,
+    pub(crate) energy: usize,
+    pub(crate) ore: usize,
+    pub(crate) science: usize,
+    pub(crate) position_x: usize,
+    pub(crate) position_y: usize,
+    // Case reserved by each collector heading to a resource, a deposit takes several if it is
+    // big enough
+    reserved_resources: Vec<(usize, usize)>,
+    // Type of the last reserved resource, for the selectors taking turns
+    #[serde(default)]
+    last_reserved: Option<TypeCase>,
+    // Known resources no known path leads to, until the known map changes
+    #[serde(default)]
+    unreachable: BTreeSet<(usize, usize)>,
+    // Robots out of battery waiting for a collector (id, x, y), oldest first
+    #[serde(default)]
+    stranded: Vec<(usize, usize, usize)>,
+    // Robots reached by a collector, recharged on their next update
+    #[serde(default)]
+    rescued: Vec<usize>,
+    // Frontier case each explorer is heading to (id, x, y), so that they spread out
+    #[serde(default)]
+    frontiers: Vec<(usize, usize, usize)>,
+    // Sector of the map each explorer explores first (id, index in the grid of sectors)
+    #[serde(default)]
+    sector_assignments: Vec<(usize, usize)>,
     config: Config,
+    // Cheapest way from every case of the known map to the base, computed when needed then
+    // updated with the map
+    #[serde(skip)]
+    distance_field: Option<DistanceField>,
+    // Moves left to the collector being given a resource, while the selector runs
+    #[serde(skip)]
+    trip_budget: Option<usize>,
+    // Known cases next to the unknown, computed when needed
+    #[serde(skip)]
+    frontier_cases: Option<Vec<(usize, usize)>>,
+    // Events of the current tick, collected by the simulation at the end of each step
+    #[serde(skip)]
+    pub(crate) events: Vec<Event>,
 }
 
 impl Base {
     pub fn new(
-        width: usize,
-        height: usize,
         position_x: usize,
         position_y: usize,
-        real_map: Arc<Mutex<Vec<Vec<TypeCase>>>>,
-        known_map: Arc<Mutex<Vec<Vec<TypeCase>>>>,
+        real_map: Map,
+        known_map: Map,
+        deposits: Deposits,
         config: Config,
-    ) -> Arc<Mutex<Self>> {
-        let deployed_robots = Arc::new(Mutex::new(Vec::new()));
-        let energy = Arc::new(Mutex::new(config.base.initial_energy));
-        let ore = Arc::new(Mutex::new(config.base.initial_ore));
-        let science = Arc::new(Mutex::new(config.base.initial_science));
-        let reserved_resources = Arc::new(Mutex::new(HashSet::new()));
-
-        let base = Arc::new(Mutex::new(Base {
+    ) -> Self {
+        let mut base = Base {
             real_map,
             known_map,
-            deployed_robots,
-            energy,
-            ore,
-            science,
+            deposits,
+            deployed_robots: Vec::new(),
+            energy: config.base.initial_energy,
+            ore: config.base.initial_ore,
+            science: config.base.initial_science,
             position_x,
             position_y,
-            reserved_resources,
+            reserved_resources: Vec::new(),
+            last_reserved: None,
+            unreachable: BTreeSet::new(),
+            stranded: Vec::new(),
+            rescued: Vec::new(),
+            frontiers: Vec::new(),
+            sector_assignments: Vec::new(),
             config,
-        }));
+            distance_field: None,
+            trip_budget: None,
+            frontier_cases: None,
+            events: Vec::new(),
+        };
 
         // Ajout des robots initiaux
-        if let Ok(mut base_guard) = base.lock() {
-            for _ in 0..base_guard.config.base.initial_explorers {
-                base_guard.add_robot(Box::new(Explorer::new(
-                    width,
-                    height,
-                    position_x,
-                    position_y,
-                    Arc::clone(&base),
-                )));
-            }
-            for _ in 0..base_guard.config.base.initial_collectors {
-                base_guard.add_robot(Box::new(Collector::new(
-                    position_x,
-                    position_y,
-                    Arc::clone(&base),
-                )));
-            }
+        for _ in 0..base.config.base.initial_explorers {
+            let explorer =
+                Explorer::new(base.next_robot_id(), position_x, position_y, &base.config);
+            base.add_robot(Box::new(explorer));
+        }
+        for _ in 0..base.config.base.initial_collectors {
+            let collector =
+                Collector::new(base.next_robot_id(), position_x, position_y, &base.config);
+            base.add_robot(Box::new(collector));
         }
 
         base
     }
 
-    pub fn start_base_thread(base: Arc<Mutex<Base>>, map_width: usize, map_height: usize) {
-        thread::spawn(move || loop {
-            let mut explorers_count = 0;
-            let mut collectors_count = 0;
-            let mut energy = 0;
-            let mut ore = 0;
-            let mut science = 0;
-            let mut position_x = 0;
-            let mut position_y = 0;
-            let mut config = None;
-
-            // Init variables
-            if let Ok(base_guard) = base.lock() {
-                position_x = base_guard.position_x;
-                position_y = base_guard.position_y;
-                config = Some(base_guard.config.clone());
-
-                if let Ok(robots) = base_guard.deployed_robots.lock() {
-                    for robot in robots.iter() {
-                        match robot.get_type() {
-                            TypeCase::Explorer => explorers_count += 1,
-                            TypeCase::Collector => collectors_count += 1,
-                            _ => {}
-                        }
-                    }
-                }
+    // Production cycle of the base, called by the simulation every production interval
+    pub fn produce_robots(&mut self) {
+        let strategy = production::from_config(&self.config.base.production);
+        self.produce_with(strategy.as_ref());
+    }
 
-                if let Ok(e) = base_guard.energy.lock() {
-                    energy = *e;
-                }
-                if let Ok(m) = base_guard.ore.lock() {
-                    ore = *m;
-                }
-                if let Ok(s) = base_guard.science.lock() {
-                    science = *s;
-                }
-            }
+    // Build the robot chosen by the strategy, if the base can afford it
+    pub fn produce_with(&mut self, strategy: &dyn ProductionStrategy) {
+        let Some(kind) = strategy.choose(self) else {
+            return;
+        };
+        if !self.can_afford(&kind) {
+            return;
+        }
 
-            if let Some(config) = config {
-                // Calculate the ratio and determine which robot to create
-                let current_ratio = if explorers_count == 0 {
-                    0.0
-                } else {
-                    collectors_count as f32 / explorers_count as f32
-                };
-
-                let create_collector = current_ratio < 2.0
-                    && collectors_count > 0
-                    && science >= config.robots.collector.cost_science
-                    && ore >= config.robots.collector.cost_ore
-                    && energy >= config.robots.collector.cost_energy;
-
-                let create_explorer = (current_ratio >= 2.0 || explorers_count == 0)
-                    && science >= config.robots.explorer.cost_science
-                    && ore >= config.robots.explorer.cost_ore
-                    && energy >= config.robots.explorer.cost_energy;
-
-                //Create robots
-                if create_collector || create_explorer {
-                    if let Ok(mut base_guard) = base.lock() {
-                        if create_collector {
-                            if let Ok(mut s) = base_guard.science.lock() {
-                                *s -= config.robots.collector.cost_science;
-                            }
-                            if let Ok(mut m) = base_guard.ore.lock() {
-                                *m -= config.robots.collector.cost_ore;
-                            }
-                            if let Ok(mut e) = base_guard.energy.lock() {
-                                *e -= config.robots.collector.cost_energy;
-                            }
-
-                            base_guard.add_robot(Box::new(Collector::new(
-                                position_x,
-                                position_y,
-                                Arc::clone(&base),
-                            )));
-                        } else if create_explorer {
-                            if let Ok(mut s) = base_guard.science.lock() {
-                                *s -= config.robots.explorer.cost_science;
-                            }
-                            if let Ok(mut m) = base_guard.ore.lock() {
-                                *m -= config.robots.explorer.cost_ore;
-                            }
-                            if let Ok(mut e) = base_guard.energy.lock() {
-                                *e -= config.robots.explorer.cost_energy;
-                            }
-
-                            base_guard.add_robot(Box::new(Explorer::new(
-                                map_width,
-                                map_height,
-                                position_x,
-                                position_y,
-                                Arc::clone(&base),
-                            )));
-                        }
-                    }
-                }
+        let robots = &self.config.robots;
+        let id = self.next_robot_id();
+        let robot: Box<dyn Robot + Send> = match kind {
+            TypeCase::Collector => {
+                self.spend(
+                    robots.collector.cost_energy,
+                    robots.collector.cost_ore,
+                    robots.collector.cost_science,
+                );
+                Box::new(Collector::new(
+                    id,
+                    self.position_x,
+                    self.position_y,
+                    &self.config,
+                ))
+            }
+            TypeCase::Explorer => {
+                self.spend(
+                    robots.explorer.cost_energy,
+                    robots.explorer.cost_ore,
+                    robots.explorer.cost_science,
+                );
+                Box::new(Explorer::new(
+                    id,
+                    self.position_x,
+                    self.position_y,
+                    &self.config,
+                ))
             }
+            _ => return,
+        };
+        self.add_robot(robot);
+    }
+
+    // Whether the stock covers the cost of a robot of this kind
+    pub fn can_afford(&self, kind: &TypeCase) -> bool {
+        let robots = &self.config.robots;
+        let (energy, ore, science) = match kind {
+            TypeCase::Collector => (
+                robots.collector.cost_energy,
+                robots.collector.cost_ore,
+                robots.collector.cost_science,
+            ),
+            TypeCase::Explorer => (
+                robots.explorer.cost_energy,
+                robots.explorer.cost_ore,
+                robots.explorer.cost_science,
+            ),
+            _ => return false,
+        };
+        self.energy >= energy + self.energy_reserve() && self.ore >= ore && self.science >= science
+    }
+
+    // Energy production leaves in the stock, enough to fill the battery of a collector so
+    // that it can always go and fetch more
+    pub fn energy_reserve(&self) -> usize {
+        self.config
+            .robots
+            .collector
+            .battery
+            .as_ref()
+            .map_or(0, |battery| {
+                battery.capacity.div_ceil(battery.charge_per_energy.max(1))
+            })
+    }
 
-            thread::sleep(Duration::from_secs(4));
+    fn spend(&mut self, energy: usize, ore: usize, science: usize) {
+        self.energy -= energy;
+        self.ore -= ore;
+        self.science -= science;
+        self.log(Event::ResourcesSpent {
+            energy,
+            ore,
+            science,
         });
     }
 
     pub fn add_robot(&mut self, robot: Box<dyn Robot + Send>) {
-        self.deployed_robots.lock().unwrap().push(robot);
+        self.log(Event::RobotSpawned {
+            robot: robot.get_id(),
+            kind: robot.get_type(),
+            x: robot.get_position_x(),
+            y: robot.get_position_y(),
+        });
+        self.deployed_robots.push(robot);
     }
 
-    pub fn update_map(&self, x: usize, y: usize, case: TypeCase) {
-        let mut map = self.known_map.lock().unwrap();
-        if x < map[0].len() && y < map.len() {
-            map[y][x] = case;
+    // Robots are never removed, their index in the deployment order is their id
+    pub fn next_robot_id(&self) -> usize {
+        self.deployed_robots.len()
+    }
+
+    pub fn get_config(&self) -> &Config {
+        &self.config
+    }
+
+    // The fields are read through these accessors, the maps and the stock only change through
+    // the methods that keep the caches and the reservations in step
+    pub fn real_map(&self) -> &Map {
+        &self.real_map
+    }
+
+    pub fn known_map(&self) -> &Map {
+        &self.known_map
+    }
+
+    pub fn robots(&self) -> &[Box<dyn Robot + Send>] {
+        &self.deployed_robots
+    }
+
+    pub fn energy(&self) -> usize {
+        self.energy
+    }
+
+    pub fn ore(&self) -> usize {
+        self.ore
+    }
+
+    pub fn science(&self) -> usize {
+        self.science
+    }
+
+    pub fn position(&self) -> (usize, usize) {
+        (self.position_x, self.position_y)
+    }
+
+    pub fn log(&mut self, event: Event) {
+        self.events.push(event);
+    }
+
+    // Number of deployed (explorers, collectors)
+    pub fn robot_counts(&self) -> (usize, usize) {
+        let mut explorers = 0;
+        let mut collectors = 0;
+        for robot in self.deployed_robots.iter() {
+            match robot.get_type() {
+                TypeCase::Explorer => explorers += 1,
+                TypeCase::Collector => collectors += 1,
+                _ => {}
+            }
         }
+        (explorers, collectors)
     }
 
-    pub fn next_resource(&self) -> Option<(usize, usize)> {
-        let known_map = self.known_map.lock().unwrap();
-        let energy_count = *self.energy.lock().unwrap();
-        let ore_count = *self.ore.lock().unwrap();
-        let science_count = *self.science.lock().unwrap();
-        let reserved = self.reserved_resources.lock().unwrap();
+    // Share of the map already revealed in the known map, between 0 and 1
+    pub fn explored_ratio(&self) -> f64 {
+        let total = self.width() * self.height();
+        let known = self
+            .known_map
+            .iter()
+            .flatten()
+            .filter(|case| **case != TypeCase::Unknown)
+            .count();
+        known as f64 / total as f64
+    }
 
-        let height = known_map.len();
-        let width = known_map[0].len();
+    pub fn width(&self) -> usize {
+        self.known_map[0].len()
+    }
 
-        // Find the highest resource counter
-        let max_resource_count = energy_count.max(ore_count).max(science_count);
+    pub fn height(&self) -> usize {
+        self.known_map.len()
+    }
+
+    pub fn update_map(&mut self, x: usize, y: usize, case: TypeCase) {
+        if x < self.width() && y < self.height() && self.known_map[y][x] != case {
+            if is_walkable(&self.known_map[y][x]) != is_walkable(&case) {
+                self.unreachable.clear();
+            }
+            let previous = std::mem::replace(&mut self.known_map[y][x], case.clone());
+            if let Some(field) = &mut self.distance_field {
+                let pathfinder = Pathfinder::from_config(&self.config.pathfinding);
+                field.update(x, y, &previous, &self.known_map, &pathfinder);
+            }
+            self.frontier_cases = None;
+            self.log(Event::TileRevealed { x, y, case });
+        }
+    }
 
-        let mut priority_queue: BinaryHeap<PrioritizedResource> = BinaryHeap::new();
+    // Reserve the resource chosen by the configured selector for a collector with `moves_left`
+    // moves of battery, among the resources it can reach and come back from
+    pub fn next_resource(&mut self, moves_left: usize) -> Option<(usize, usize)> {
+        let selector = selection::from_config(&self.config.base.resource_selection);
+        self.next_resource_with(selector.as_ref(), moves_left)
+    }
 
-        // Explore the entire map to find resources
-        for y in 0..height {
-            for x in 0..width {
-                // Check if the case is not already reserved
-                if reserved.contains(&(x, y)) {
-                    continue;
-                }
+    pub fn next_resource_with(
+        &mut self,
+        selector: &dyn ResourceSelector,
+        moves_left: usize,
+    ) -> Option<(usize, usize)> {
+        // The selectors rank the resources with the distance field, computed once here
+        self.distance_field();
+        self.trip_budget = Some(moves_left);
+        let selected = loop {
+            let Some((x, y)) = selector.select(self) else {
+                break None;
+            };
+            if self.home_distance(x, y).is_some() {
+                break Some((x, y));
+            }
+            self.mark_unreachable(x, y);
+        };
+        self.trip_budget = None;
+        let (x, y) = selected?;
+        self.reserved_resources.push((x, y));
+        self.last_reserved = Some(self.known_map[y][x].clone());
+        self.log(Event::ResourceReserved { x, y });
+        Some((x, y))
+    }
 
-                let case = &known_map[y][x];
-                match case {
-                    TypeCase::Energy | TypeCase::Ore | TypeCase::Science => {
-                        // Calculate the distance between the resource and the base
-                        let distance =
-                            Self::manhattan_distance(self.position_x, self.position_y, x, y);
-
-                        // Calculate the priority level based on the difference with the highest counter
-                        let priority_level = match case {
-                            TypeCase::Energy => max_resource_count.saturating_sub(energy_count),
-                            TypeCase::Ore => max_resource_count.saturating_sub(ore_count),
-                            TypeCase::Science => max_resource_count.saturating_sub(science_count),
-                            _ => 0,
-                        };
-
-                        priority_queue.push(PrioritizedResource {
-                            x,
-                            y,
-                            distance,
-                            priority_level,
-                        });
-                    }
-                    _ => continue,
+    // Resources of the known map with units left for one more collector, row by row
+    pub fn available_resources(&self) -> Vec<(usize, usize, TypeCase)> {
+        let capacity = self.collector_capacity();
+        let mut resources = Vec::new();
+        for (y, row) in self.known_map.iter().enumerate() {
+            for (x, case) in row.iter().enumerate() {
+                if matches!(case, TypeCase::Energy | TypeCase::Ore | TypeCase::Science)
+                    && self.reservations(x, y) * capacity < self.deposit(x, y)
+                    && !self.unreachable.contains(&(x, y))
+                    && self.within_budget(x, y)
+                {
+                    resources.push((x, y, case.clone()));
                 }
             }
         }
+        resources
+    }
 
-        // Take the most prioritized resource
-        if let Some(resource) = priority_queue.pop() {
-            drop(known_map);
-            drop(reserved);
-            self.reserved_resources
-                .lock()
-                .unwrap()
-                .insert((resource.x, resource.y));
-            return Some((resource.x, resource.y));
-        }
+    // Whether the trip to a case fits the battery of the collector being given a resource,
+    // cases without a known path are left to the caller
+    fn within_budget(&self, x: usize, y: usize) -> bool {
+        let Some(budget) = self.trip_budget else {
+            return true;
+        };
+        self.computed_distance_field()
+            .and_then(|field| field.moves(x, y))
+            .is_none_or(|distance| round_trip(distance) <= budget)
+    }
 
-        None
+    // Moves of the trip from the base to a case and back, None when no known path leads there
+    pub fn round_trip(&mut self, x: usize, y: usize) -> Option<usize> {
+        self.home_distance(x, y).map(round_trip)
+    }
+
+    // Whether the known map still shows a resource with units left at a case
+    pub fn has_resource(&self, x: usize, y: usize) -> bool {
+        matches!(
+            self.known_map[y][x],
+            TypeCase::Energy | TypeCase::Ore | TypeCase::Science
+        ) && self.deposit(x, y) > 0
     }
 
-    fn manhattan_distance(x1: usize, y1: usize, x2: usize, y2: usize) -> usize {
-        ((x1 as isize - x2 as isize).abs() + (y1 as isize - y2 as isize).abs()) as usize
+    // Whether robots may plan a path through a case of the known map
+    pub fn is_walkable(&self, x: usize, y: usize) -> bool {
+        self.known_map
+            .get(y)
+            .and_then(|row| row.get(x))
+            .is_some_and(is_walkable)
     }
 
-    pub fn release_resource(&self, x: usize, y: usize) {
-        if let Ok(mut reserved) = self.reserved_resources.lock() {
-            reserved.remove(&(x, y));
+    // Skip a resource until the known map changes, no path leads to it for now
+    pub fn mark_unreachable(&mut self, x: usize, y: usize) {
+        if self.unreachable.insert((x, y)) {
+            self.log(Event::ResourceUnreachable { x, y });
         }
     }
 
-    pub fn add_resource(&self, resource: TypeCase) {
+    pub fn last_reserved(&self) -> Option<&TypeCase> {
+        self.last_reserved.as_ref()
+    }
+
+    // Stock of a resource type, 0 for the other cases
+    pub fn stock(&self, resource: &TypeCase) -> usize {
         match resource {
-            TypeCase::Energy => {
-                if let Ok(mut energy) = self.energy.lock() {
-                    *energy += 1;
-                }
+            TypeCase::Energy => self.energy,
+            TypeCase::Ore => self.ore,
+            TypeCase::Science => self.science,
+            _ => 0,
+        }
+    }
+
+    // Cheapest path over the known map with the configured movement rules, both ends included.
+    // Trips from or to the base are read from the distance field instead of searched
+    pub fn find_path(
+        &mut self,
+        start: (usize, usize),
+        goal: (usize, usize),
+    ) -> Option<Vec<(usize, usize)>> {
+        let home = (self.position_x, self.position_y);
+        if goal == home {
+            return self.distance_field().path(start.0, start.1);
+        }
+        if start == home {
+            let mut path = self.distance_field().path(goal.0, goal.1)?;
+            path.reverse();
+            return Some(path);
+        }
+        Pathfinder::from_config(&self.config.pathfinding).find_path(start, goal, &self.known_map)
+    }
+
+    // Number of moves of the cheapest known path from a case to the base, None when there is none
+    pub fn home_distance(&mut self, x: usize, y: usize) -> Option<usize> {
+        self.distance_field().moves(x, y)
+    }
+
+    // Cheapest way from every case of the known map to the base
+    pub fn distance_field(&mut self) -> &DistanceField {
+        let home = (self.position_x, self.position_y);
+        let known_map = &self.known_map;
+        let config = &self.config.pathfinding;
+        self.distance_field.get_or_insert_with(|| {
+            DistanceField::new(home, known_map, &Pathfinder::from_config(config))
+        })
+    }
+
+    // The distance field if it was already computed, for the callers that cannot compute it
+    pub fn computed_distance_field(&self) -> Option<&DistanceField> {
+        self.distance_field.as_ref()
+    }
+
+    // Known case a robot can walk on next to an unknown one
+    pub fn is_frontier(&self, x: usize, y: usize) -> bool {
+        if !is_walkable(&self.known_map[y][x]) {
+            return false;
+        }
+        NEIGHBOURS[..4].iter().any(|&offset| {
+            let Some((nx, ny)) = neighbour(x, y, offset) else {
+                return false;
+            };
+            self.known_map
+                .get(ny)
+                .and_then(|row| row.get(nx))
+                .is_some_and(|case| *case == TypeCase::Unknown)
+        })
+    }
+
+    // Closest frontier the explorer can reach and come back from with `moves_left`, away from
+    // the frontiers of the other explorers and inside its sector if possible, claimed until the
+    // explorer picks another one. Returns the path to it, reversed like `find_path(frontier,
+    // position)` so that the battery check counts the moves the explorer will make
+    pub fn next_frontier(
+        &mut self,
+        robot: usize,
+        x: usize,
+        y: usize,
+        moves_left: usize,
+    ) -> Option<Vec<(usize, usize)>> {
+        self.release_frontier(robot);
+        let sector = self.explorer_sector(robot, x, y);
+        // Reaching a frontier reveals the cases around it, the other explorers keep away from them
+        let spread = self.config.robots.explorer.vision_range;
+        let mut candidates = self.frontier_cases().to_vec();
+        candidates.retain(|&(fx, fy)| {
+            self.frontiers
+                .iter()
+                .all(|&(_, cx, cy)| fx.abs_diff(cx) + fy.abs_diff(cy) > spread)
+        });
+        if candidates.is_empty() {
+            return None;
+        }
+
+        // The known ways from the base tell which frontiers can be reached and how far they are
+        // from home, the frontiers closest to the sector come first, then the closest to the
+        // explorer by the estimate of the configured heuristic
+        let pathfinder = Pathfinder::from_config(&self.config.pathfinding);
+        let mut reachable: Vec<(usize, u64, usize, usize, usize)> = candidates
+            .into_iter()
+            .filter(|&frontier| frontier != (x, y))
+            .filter_map(|(fx, fy)| {
+                let home = self.home_distance(fx, fy)?;
+                let outside = sector.map_or(0, |sector| sector.distance(fx, fy));
+                let estimate = pathfinder.heuristic.estimate((x, y), (fx, fy));
+                Some((outside, estimate, fy, fx, home))
+            })
+            .collect();
+        reachable.sort_unstable();
+
+        // Only the frontiers picked are searched, until one leaves enough battery to come back
+        for (_, _, fy, fx, home) in reachable {
+            let fewest_moves = if pathfinder.diagonal {
+                fx.abs_diff(x).max(fy.abs_diff(y))
+            } else {
+                fx.abs_diff(x) + fy.abs_diff(y)
+            };
+            if moves_left != usize::MAX && fewest_moves + home > moves_left {
+                continue;
             }
-            TypeCase::Ore => {
-                if let Ok(mut ore) = self.ore.lock() {
-                    *ore += 1;
-                }
+            let Some(path) = self.find_path((fx, fy), (x, y)) else {
+                continue;
+            };
+            // The cheapest way home from a case of the path can take more moves than the rest of
+            // the path and the way home from the frontier, so every case is checked
+            let fits = moves_left == usize::MAX
+                || path.iter().rev().enumerate().all(|(moves, &(px, py))| {
+                    self.home_distance(px, py)
+                        .is_some_and(|home| moves + home <= moves_left)
+                });
+            if fits {
+                self.frontiers.push((robot, fx, fy));
+                return Some(path);
             }
-            TypeCase::Science => {
-                if let Ok(mut science) = self.science.lock() {
-                    *science += 1;
+        }
+        None
+    }
+
+    // Every frontier of the known map, row by row
+    fn frontier_cases(&mut self) -> &[(usize, usize)] {
+        if self.frontier_cases.is_none() {
+            let mut cases = Vec::new();
+            for y in 0..self.height() {
+                for x in 0..self.width() {
+                    if self.is_frontier(x, y) {
+                        cases.push((x, y));
+                    }
                 }
             }
-            _ => {}
+            self.frontier_cases = Some(cases);
+        }
+        self.frontier_cases.as_deref().unwrap_or_default()
+    }
+
+    pub fn release_frontier(&mut self, robot: usize) {
+        self.frontiers.retain(|&(id, _, _)| id != robot);
+    }
+
+    // Sector the explorer explores first, kept until it is fully revealed, then the open sector
+    // with the fewest explorers, closest first, None without sectors or once all are revealed
+    fn explorer_sector(&mut self, robot: usize, x: usize, y: usize) -> Option<Sector> {
+        let grid = match &self.config.robots.explorer.sectors {
+            Some(config) => sectors::grid(self.width(), self.height(), config),
+            None => return None,
+        };
+        let open: Vec<bool> = grid.iter().map(|sector| self.is_open(sector)).collect();
+
+        let assigned = self
+            .sector_assignments
+            .iter()
+            .find(|&&(id, _)| id == robot)
+            .map(|&(_, index)| index);
+        if let Some(index) = assigned {
+            if open.get(index) == Some(&true) {
+                return Some(grid[index]);
+            }
         }
+        self.release_sector(robot);
+
+        let assignments = &self.sector_assignments;
+        let (index, sector) = grid
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| open[*index])
+            .min_by_key(|(index, sector)| {
+                let explorers = assignments.iter().filter(|(_, i)| i == index).count();
+                let (cx, cy) = sector.center();
+                (explorers, cx.abs_diff(x) + cy.abs_diff(y))
+            })?;
+        self.sector_assignments.push((robot, index));
+        Some(*sector)
     }
 
-    pub fn get_explorer_config(&self) -> ExplorerConfig {
-        self.config.robots.explorer.clone()
+    fn release_sector(&mut self, robot: usize) {
+        self.sector_assignments.retain(|&(id, _)| id != robot);
     }
 
-    pub fn get_collector_config(&self) -> CollectorConfig {
-        self.config.robots.collector.clone()
+    // A sector is fully revealed once it has been reached and has no frontier left inside
+    fn is_open(&mut self, sector: &Sector) -> bool {
+        let reached = (sector.y0..sector.y1)
+            .any(|y| (sector.x0..sector.x1).any(|x| self.known_map[y][x] != TypeCase::Unknown));
+        !reached
+            || self
+                .frontier_cases()
+                .iter()
+                .any(|&(x, y)| sector.contains(x, y))
     }
+
+    pub fn report_stranded(&mut self, robot: usize, x: usize, y: usize) {
+        self.release_frontier(robot);
+        self.release_sector(robot);
+        self.stranded.push((robot, x, y));
+        self.log(Event::RobotStranded { robot, x, y });
+    }
+
+    // Take the oldest stranded robot, to be rescued by a collector
+    pub fn claim_stranded(&mut self) -> Option<(usize, (usize, usize))> {
+        if self.stranded.is_empty() {
+            return None;
+        }
+        let (robot, x, y) = self.stranded.remove(0);
+        Some((robot, (x, y)))
+    }
+
+    // Give a stranded robot back when the collector could not go to it
+    pub fn unclaim_stranded(&mut self, robot: usize, x: usize, y: usize) {
+        self.stranded.push((robot, x, y));
+    }
+
+    pub fn rescue(&mut self, robot: usize, by: usize) {
+        self.rescued.push(robot);
+        self.log(Event::RobotRescued { robot, by });
+    }
+
+    // Whether a collector reached this robot since its last update
+    pub fn take_rescue(&mut self, robot: usize) -> bool {
+        match self.rescued.iter().position(|&id| id == robot) {
+            Some(index) => {
+                self.rescued.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Units left in the deposit of a case, none outside of the map
+    pub fn deposit(&self, x: usize, y: usize) -> usize {
+        self.deposits
+            .get(y)
+            .and_then(|row| row.get(x))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub fn collector_capacity(&self) -> usize {
+        self.config.robots.collector.capacity.max(1)
+    }
+
+    // Take up to one load from the deposit of a case, which becomes void once depleted
+    pub fn collect_resource(&mut self, robot: usize, x: usize, y: usize) -> (TypeCase, usize) {
+        let resource = self.real_map[y][x].clone();
+        let left = self.deposit(x, y);
+        let amount = left.min(self.collector_capacity());
+        if let Some(deposit) = self.deposits.get_mut(y).and_then(|row| row.get_mut(x)) {
+            *deposit = left - amount;
+        }
+        self.log(Event::ResourceCollected {
+            robot,
+            x,
+            y,
+            resource: resource.clone(),
+            amount,
+        });
+
+        if left == amount {
+            self.real_map[y][x] = TypeCase::Void;
+            self.update_map(x, y, TypeCase::Void);
+        }
+        self.release_resource(x, y);
+        (resource, amount)
+    }
+
+    pub fn release_resource(&mut self, x: usize, y: usize) {
+        if let Some(index) = self
+            .reserved_resources
+            .iter()
+            .position(|&case| case == (x, y))
+        {
+            self.reserved_resources.swap_remove(index);
+            self.log(Event::ResourceReleased { x, y });
+        }
+    }
+
+    // Number of collectors heading to a case
+    pub fn reservations(&self, x: usize, y: usize) -> usize {
+        self.reserved_resources
+            .iter()
+            .filter(|&&case| case == (x, y))
+            .count()
+    }
+
+    pub fn add_resource(&mut self, resource: TypeCase, amount: usize) {
+        match resource {
+            TypeCase::Energy => self.energy += amount,
+            TypeCase::Ore => self.ore += amount,
+            TypeCase::Science => self.science += amount,
+            _ => {}
+        }
+    }
+}
+
+// Each way of a trip starts with a move onto the case the robot stands on
+fn round_trip(distance: usize) -> usize {
+    2 * (distance + 1)
+}
+
+// Whether collectors may plan a path through a case of the known map
+fn is_walkable(case: &TypeCase) -> bool {
+    *case != TypeCase::Wall && *case != TypeCase::Unknown
 }
@@ -0,0 +1,63 @@
+use crate::base::Base;
+use crate::config::BatteryConfig;
+use crate::events::Event;
+use serde::{Deserialize, Serialize};
+
+// Charge of a robot, drained by every move and refilled from the energy stock of the base
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Battery {
+    pub capacity: usize,
+    pub charge: usize,
+    drain_per_move: usize,
+    charge_per_energy: usize,
+}
+
+impl Battery {
+    pub fn new(config: &BatteryConfig) -> Self {
+        Battery {
+            capacity: config.capacity,
+            charge: config.capacity,
+            drain_per_move: config.drain_per_move,
+            charge_per_energy: config.charge_per_energy.max(1),
+        }
+    }
+
+    // Number of moves the robot can still make
+    pub fn moves_left(&self) -> usize {
+        match self.charge.checked_div(self.drain_per_move) {
+            Some(moves) => moves,
+            None => usize::MAX,
+        }
+    }
+
+    pub fn can_move(&self) -> bool {
+        self.moves_left() > 0
+    }
+
+    pub fn drain(&mut self) {
+        self.charge = self.charge.saturating_sub(self.drain_per_move);
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.charge >= self.capacity
+    }
+
+    // Fill the battery with as much energy as the base can give
+    pub fn recharge(&mut self, robot: usize, base: &mut Base) {
+        let missing = self.capacity.saturating_sub(self.charge);
+        let energy = missing.div_ceil(self.charge_per_energy).min(base.energy);
+        if energy == 0 {
+            return;
+        }
+        base.energy -= energy;
+        self.charge = (self.charge + energy * self.charge_per_energy).min(self.capacity);
+        base.log(Event::RobotRecharged { robot, energy });
+    }
+
+    // Share of the capacity left, in percent
+    pub fn percent(&self) -> usize {
+        (self.charge * 100)
+            .checked_div(self.capacity)
+            .unwrap_or(100)
+    }
+}
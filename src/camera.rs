@@ -0,0 +1,132 @@
+use crate::ui::Scene;
+use crossterm::event::KeyCode;
+
+// Highest zoom level, each level above 1 doubles the number of cases per character
+const MAX_ZOOM: usize = 4;
+const PAN_STEP: usize = 2;
+
+// Part of the map visible on screen, in map coordinates
+pub struct Viewport {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    // Number of cases along each axis drawn in a single character
+    pub scale: usize,
+    // One character per cell instead of the two columns wide emojis
+    pub condensed: bool,
+}
+
+pub struct Camera {
+    center_x: usize,
+    center_y: usize,
+    // 0 shows the emojis, 1 one character per case, then 2x2, 4x4... cases per character
+    zoom: usize,
+    pub selected_robot: usize,
+    pub following: bool,
+}
+
+impl Camera {
+    pub fn new(center_x: usize, center_y: usize) -> Self {
+        Camera {
+            center_x,
+            center_y,
+            zoom: 0,
+            selected_robot: 0,
+            following: false,
+        }
+    }
+
+    // Apply a key press, returns false when the key is not a camera control
+    pub fn handle_key(&mut self, code: KeyCode, scene: &Scene) -> bool {
+        let step = PAN_STEP * self.scale();
+        match code {
+            KeyCode::Up | KeyCode::Char('w') => self.pan(0, -(step as isize), scene),
+            KeyCode::Down | KeyCode::Char('s') => self.pan(0, step as isize, scene),
+            KeyCode::Left | KeyCode::Char('a') => self.pan(-(step as isize), 0, scene),
+            KeyCode::Right | KeyCode::Char('d') => self.pan(step as isize, 0, scene),
+            KeyCode::Char('+') | KeyCode::Char('=') => self.zoom = self.zoom.saturating_sub(1),
+            KeyCode::Char('-') => self.zoom = (self.zoom + 1).min(MAX_ZOOM),
+            KeyCode::Char('b') => {
+                self.following = false;
+                self.center_x = scene.base_x;
+                self.center_y = scene.base_y;
+            }
+            KeyCode::Char('f') => self.following = !self.following,
+            KeyCode::Tab => self.select_robot(1, scene),
+            KeyCode::BackTab => self.select_robot(-1, scene),
+            _ => return false,
+        }
+        true
+    }
+
+    // Keep the followed robot in the center of the screen
+    pub fn update(&mut self, scene: &Scene) {
+        if !self.following {
+            return;
+        }
+        match scene.robots.get(self.selected_robot) {
+            Some(robot) => {
+                self.center_x = robot.x;
+                self.center_y = robot.y;
+            }
+            None => self.following = false,
+        }
+    }
+
+    pub fn viewport(
+        &self,
+        map_width: usize,
+        map_height: usize,
+        columns: usize,
+        rows: usize,
+    ) -> Viewport {
+        let scale = self.scale();
+        let condensed = self.zoom > 0;
+        let (width, height) = if condensed {
+            (columns * scale, rows * scale)
+        } else {
+            (columns / 2, rows)
+        };
+
+        Viewport {
+            x: Self::origin(self.center_x, width, map_width),
+            y: Self::origin(self.center_y, height, map_height),
+            width: width.min(map_width),
+            height: height.min(map_height),
+            scale,
+            condensed,
+        }
+    }
+
+    fn scale(&self) -> usize {
+        if self.zoom <= 1 {
+            1
+        } else {
+            1 << (self.zoom - 1)
+        }
+    }
+
+    // First visible case so that `center` is in the middle without leaving the map
+    fn origin(center: usize, visible: usize, size: usize) -> usize {
+        if visible >= size {
+            return 0;
+        }
+        center.saturating_sub(visible / 2).min(size - visible)
+    }
+
+    fn pan(&mut self, dx: isize, dy: isize, scene: &Scene) {
+        self.following = false;
+        self.center_x = (self.center_x as isize + dx).clamp(0, scene.width() as isize - 1) as usize;
+        self.center_y =
+            (self.center_y as isize + dy).clamp(0, scene.height() as isize - 1) as usize;
+    }
+
+    fn select_robot(&mut self, offset: isize, scene: &Scene) {
+        let count = scene.robots.len() as isize;
+        if count == 0 {
+            return;
+        }
+        self.selected_robot = (self.selected_robot as isize + offset).rem_euclid(count) as usize;
+    }
+}
@@ -0,0 +1,284 @@
+use rust_ereea::config::{Config, DEFAULT_CONFIG_PATH};
+use rust_ereea::mapfile;
+use rust_ereea::save::{self, DEFAULT_SAVE_PATH};
+use rust_ereea::{Scenario, Simulation};
+use std::error::Error;
+use std::str::FromStr;
+
+pub const USAGE: &str = "Utilisation :
+  rust-ereea [options du monde] [options de sortie] [--save <sauvegarde>]
+  rust-ereea headless (--ticks <n> | --duration-ms <ms> | --scenario <fichier>)
+                      [options du monde] [options de sortie] [--save <sauvegarde>]
+  rust-ereea replay --file <relecture>
+  rust-ereea export-map --output <fichier.txt | fichier.png> [options du monde]
+
+Options du monde :
+  --config <chemin>  --seed <graine>  --width <l>  --height <h>  --load <sauvegarde>
+  --map <fichier.txt | fichier.png>  --scenario <fichier>
+
+Options de sortie :
+  --record <relecture>  --metrics <fichier.csv | fichier.jsonl>";
+
+pub enum Command {
+    Run(RunOptions),
+    Headless(HeadlessOptions),
+    Replay(ReplayOptions),
+    ExportMap(ExportMapOptions),
+}
+
+// Options describing which world is simulated, shared by every command
+#[derive(Default)]
+pub struct WorldOptions {
+    // The default configuration when missing
+    pub config_path: Option<String>,
+    pub seed: Option<u32>,
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    // Resume a saved simulation instead of generating a new one
+    pub load_path: Option<String>,
+    // Load the map from a file instead of generating it, overrides map.file
+    pub map_path: Option<String>,
+    // Play a scenario, it names its own configuration so --config is refused with it
+    pub scenario_path: Option<String>,
+}
+
+// Files written while the simulation runs, shared by the interface and the headless mode
+#[derive(Default)]
+pub struct OutputOptions {
+    // Record every event in a replay file
+    pub record_path: Option<String>,
+    // Write metrics at a regular interval, as CSV when the file ends with .csv, else JSON lines
+    pub metrics_path: Option<String>,
+}
+
+pub struct RunOptions {
+    pub world: WorldOptions,
+    pub output: OutputOptions,
+    // Where the simulation is written when saving
+    pub save_path: String,
+}
+
+pub struct HeadlessOptions {
+    pub world: WorldOptions,
+    pub output: OutputOptions,
+    // Runs until the end of the scenario when missing
+    pub limit: Option<Limit>,
+    // Save the simulation at the end of the run
+    pub save_path: Option<String>,
+}
+
+pub struct ReplayOptions {
+    pub path: String,
+}
+
+pub struct ExportMapOptions {
+    pub world: WorldOptions,
+    // Written as a PNG when it ends with .png, else as an ASCII grid
+    pub output_path: String,
+}
+
+// How long a headless run lasts, either a number of ticks or of simulated milliseconds
+pub enum Limit {
+    Ticks(u64),
+    DurationMs(u64),
+}
+
+impl WorldOptions {
+    // Load the saved simulation or generate a new one, `fallback_size` is used when
+    // neither the command line nor the configuration gives the map size
+    pub fn build(
+        &self,
+        fallback_size: Option<(usize, usize)>,
+    ) -> Result<Simulation, Box<dyn Error>> {
+        if let Some(load_path) = &self.load_path {
+            return save::load(load_path);
+        }
+
+        let mut config = match &self.scenario_path {
+            Some(path) => Scenario::load(path)?.1,
+            None => Config::load_from(self.config_path.as_deref().unwrap_or(DEFAULT_CONFIG_PATH))?,
+        };
+        if let Some(seed) = self.seed {
+            config.map.seed = seed;
+        }
+        if let Some(map_path) = self.map_path.as_ref().or(config.map.file.as_ref()) {
+            let world = mapfile::load(map_path, &config)?;
+            return Ok(Simulation::from_world(world, config));
+        }
+        let width = self
+            .width
+            .or(config.map.width)
+            .or(fallback_size.map(|(width, _)| width))
+            .ok_or("Largeur de la carte manquante, renseignez map.width ou passez --width")?;
+        let height = self
+            .height
+            .or(config.map.height)
+            .or(fallback_size.map(|(_, height)| height))
+            .ok_or("Hauteur de la carte manquante, renseignez map.height ou passez --height")?;
+
+        Ok(Simulation::new(width, height, config))
+    }
+
+    // Goals of the scenario being played, if any
+    pub fn scenario(&self) -> Result<Option<Scenario>, Box<dyn Error>> {
+        match &self.scenario_path {
+            Some(path) => Ok(Some(Scenario::load(path)?.0)),
+            None => Ok(None),
+        }
+    }
+
+    fn parse_flag(&mut self, flag: &str, value: &str) -> Result<bool, String> {
+        match flag {
+            "--config" => self.config_path = Some(value.to_string()),
+            "--seed" => self.seed = Some(parse_value(flag, value)?),
+            "--width" => self.width = Some(parse_value(flag, value)?),
+            "--height" => self.height = Some(parse_value(flag, value)?),
+            "--load" => self.load_path = Some(value.to_string()),
+            "--map" => self.map_path = Some(value.to_string()),
+            "--scenario" => self.scenario_path = Some(value.to_string()),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    // Options that cannot be used together
+    fn check(&self) -> Result<(), String> {
+        if self.config_path.is_some() && self.scenario_path.is_some() {
+            return Err(
+                "--config ne peut pas être utilisé avec --scenario, renseignez config dans le fichier du scénario"
+                    .to_string(),
+            );
+        }
+        Ok(())
+    }
+}
+
+impl OutputOptions {
+    fn parse_flag(&mut self, flag: &str, value: &str) -> bool {
+        match flag {
+            "--record" => self.record_path = Some(value.to_string()),
+            "--metrics" => self.metrics_path = Some(value.to_string()),
+            _ => return false,
+        }
+        true
+    }
+}
+
+pub fn parse_args(args: &[String]) -> Result<Command, String> {
+    match args.first().map(String::as_str) {
+        Some("headless") => parse_headless(&args[1..]).map(Command::Headless),
+        Some("replay") => parse_replay(&args[1..]).map(Command::Replay),
+        Some("export-map") => parse_export_map(&args[1..]).map(Command::ExportMap),
+        _ => parse_run(args).map(Command::Run),
+    }
+}
+
+fn parse_run(args: &[String]) -> Result<RunOptions, String> {
+    let mut options = RunOptions {
+        world: WorldOptions::default(),
+        output: OutputOptions::default(),
+        save_path: DEFAULT_SAVE_PATH.to_string(),
+    };
+
+    for (flag, value) in flag_pairs(args)? {
+        if options.world.parse_flag(flag, value)? || options.output.parse_flag(flag, value) {
+            continue;
+        }
+        match flag {
+            "--save" => options.save_path = value.to_string(),
+            _ => return Err(format!("Option inconnue {}", flag)),
+        }
+    }
+    options.world.check()?;
+
+    Ok(options)
+}
+
+fn parse_headless(args: &[String]) -> Result<HeadlessOptions, String> {
+    let mut world = WorldOptions::default();
+    let mut output = OutputOptions::default();
+    let mut limit = None;
+    let mut save_path = None;
+
+    for (flag, value) in flag_pairs(args)? {
+        if world.parse_flag(flag, value)? || output.parse_flag(flag, value) {
+            continue;
+        }
+        match flag {
+            "--ticks" => limit = Some(Limit::Ticks(parse_value(flag, value)?)),
+            "--duration-ms" => limit = Some(Limit::DurationMs(parse_value(flag, value)?)),
+            "--save" => save_path = Some(value.to_string()),
+            _ => return Err(format!("Option inconnue {}", flag)),
+        }
+    }
+
+    world.check()?;
+    if limit.is_none() && world.scenario_path.is_none() {
+        return Err("--ticks, --duration-ms ou --scenario manquant".to_string());
+    }
+
+    Ok(HeadlessOptions {
+        world,
+        output,
+        limit,
+        save_path,
+    })
+}
+
+fn parse_replay(args: &[String]) -> Result<ReplayOptions, String> {
+    let mut path = None;
+
+    for (flag, value) in flag_pairs(args)? {
+        match flag {
+            "--file" => path = Some(value.to_string()),
+            _ => return Err(format!("Option inconnue {}", flag)),
+        }
+    }
+
+    Ok(ReplayOptions {
+        path: path.ok_or("--file manquant")?,
+    })
+}
+
+fn parse_export_map(args: &[String]) -> Result<ExportMapOptions, String> {
+    let mut world = WorldOptions::default();
+    let mut output_path = None;
+
+    for (flag, value) in flag_pairs(args)? {
+        if world.parse_flag(flag, value)? {
+            continue;
+        }
+        match flag {
+            "--output" => output_path = Some(value.to_string()),
+            _ => return Err(format!("Option inconnue {}", flag)),
+        }
+    }
+    world.check()?;
+
+    Ok(ExportMapOptions {
+        world,
+        output_path: output_path.ok_or("--output manquant")?,
+    })
+}
+
+// Split the arguments into (--flag, value) pairs
+fn flag_pairs(args: &[String]) -> Result<Vec<(&str, &str)>, String> {
+    let mut pairs = Vec::new();
+    let mut iter = args.iter();
+    while let Some(flag) = iter.next() {
+        if !flag.starts_with("--") {
+            return Err(format!("Argument inattendu {}", flag));
+        }
+        let value = iter
+            .next()
+            .ok_or_else(|| format!("Valeur manquante pour {}", flag))?;
+        pairs.push((flag.as_str(), value.as_str()));
+    }
+    Ok(pairs)
+}
+
+fn parse_value<T: FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Valeur invalide pour {} : {}", flag, value))
+}
@@ -1,55 +1,492 @@
-use serde::Deserialize;
+use crate::generation::TypeCase;
+use serde::{Deserialize, Serialize};
 use std::fs;
 
-#[derive(Debug, Deserialize, Clone)]
+pub const DEFAULT_CONFIG_PATH: &str = "config.toml";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Config {
     pub map: MapConfig,
     pub robots: RobotsConfig,
     pub base: BaseConfig,
+    #[serde(default)]
+    pub simulation: SimulationConfig,
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    #[serde(default)]
+    pub pathfinding: PathfindingConfig,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct MapConfig {
     pub seed: u32,
     pub generation_rate: f64,
+    // Size of the world in cases, the terminal size is used when they are missing
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    // ASCII or PNG map loaded instead of generating one, its size replaces width and height
+    #[serde(default)]
+    pub file: Option<String>,
+    // Units held by a resource deposit, richer where the noise is higher
+    #[serde(default = "default_deposit_size")]
+    pub deposit_min: usize,
+    #[serde(default = "default_deposit_size")]
+    pub deposit_max: usize,
+    // Resources growing back during the simulation, none by default
+    #[serde(default)]
+    pub regeneration: RegenerationConfig,
+    #[serde(default)]
+    pub terrain: TerrainConfig,
+    // Regions changing the walls and the resource density, none by default
+    #[serde(default)]
+    pub biomes: Option<BiomesConfig>,
+    // How resources walled off from the base are made reachable
+    #[serde(default)]
+    pub connectivity: Connectivity,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Connectivity {
+    // Keep the map as generated
+    #[default]
+    None,
+    // Dig through the fewest walls between each isolated resource and the base
+    Carve,
+    // Move the isolated resources to void cases reachable from the base
+    Relocate,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct TerrainConfig {
+    // Size in cases of the features of the terrain noise, along each axis
+    pub scale_x: f64,
+    pub scale_y: f64,
+    // Layers of noise added together (fractal Brownian motion), each one finer and fainter
+    pub octaves: usize,
+    pub persistence: f64,
+    pub lacunarity: f64,
+    // Walls below the first threshold, the terrain band of resources below the second one
+    pub wall_threshold: f64,
+    pub band_threshold: f64,
+    pub energy: Distribution,
+    pub ore: Distribution,
+    pub science: Distribution,
+}
+
+impl Default for TerrainConfig {
+    fn default() -> Self {
+        TerrainConfig {
+            scale_x: 9.25,
+            scale_y: 8.0,
+            octaves: 1,
+            persistence: 0.5,
+            lacunarity: 2.0,
+            wall_threshold: -0.55,
+            band_threshold: -0.53,
+            energy: Distribution::Uniform,
+            ore: Distribution::Terrain,
+            science: Distribution::Uniform,
+        }
+    }
+}
+
+// Where the cases of a resource are placed
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum Distribution {
+    // Every case of the noise band just above the walls
+    Terrain,
+    // generation_rate of the map, anywhere
+    Uniform,
+    // generation_rate of the map, where a separate noise layer is above the threshold
+    Clustered {
+        #[serde(default = "default_cluster_scale")]
+        scale: f64,
+        #[serde(default = "default_cluster_threshold")]
+        threshold: f64,
+    },
+    // generation_rate of the map, within `distance` cases of a wall
+    NearWalls {
+        #[serde(default = "default_wall_distance")]
+        distance: usize,
+    },
+}
+
+fn default_cluster_scale() -> f64 {
+    6.0
+}
+
+fn default_cluster_threshold() -> f64 {
+    0.2
 }
 
-#[derive(Debug, Deserialize, Clone)]
+fn default_wall_distance() -> usize {
+    2
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct BiomesConfig {
+    // Size in cases of the biome regions
+    pub scale: f64,
+    pub caves: BiomeConfig,
+    pub plains: BiomeConfig,
+    pub crystal_fields: BiomeConfig,
+}
+
+impl Default for BiomesConfig {
+    fn default() -> Self {
+        BiomesConfig {
+            scale: 30.0,
+            caves: BiomeConfig {
+                wall_offset: 0.15,
+                energy: 0.5,
+                ore: 2.0,
+                science: 0.5,
+            },
+            plains: BiomeConfig {
+                wall_offset: -0.15,
+                energy: 2.0,
+                ore: 0.5,
+                science: 1.0,
+            },
+            crystal_fields: BiomeConfig {
+                wall_offset: 0.0,
+                energy: 0.5,
+                ore: 1.0,
+                science: 3.0,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BiomeConfig {
+    // Added to the wall threshold, more walls when positive
+    pub wall_offset: f64,
+    // Relative density of each resource
+    pub energy: f64,
+    pub ore: f64,
+    pub science: f64,
+}
+
+impl BiomeConfig {
+    // Relative density of a resource in this biome
+    pub fn density(&self, resource: &TypeCase) -> f64 {
+        match resource {
+            TypeCase::Energy => self.energy,
+            TypeCase::Ore => self.ore,
+            TypeCase::Science => self.science,
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RegenerationConfig {
+    pub energy: Option<RegenerationRule>,
+    pub ore: Option<RegenerationRule>,
+    pub science: Option<RegenerationRule>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RegenerationRule {
+    // Simulated time between two deposits growing back
+    pub interval_ms: u64,
+    // Grow within this distance of an existing deposit of the same type, 0 for anywhere
+    #[serde(default)]
+    pub radius: usize,
+    // Number of adjacent deposits appearing together
+    #[serde(default = "default_cluster")]
+    pub cluster: usize,
+}
+
+fn default_cluster() -> usize {
+    1
+}
+
+impl RegenerationConfig {
+    pub fn rules(&self) -> Vec<(TypeCase, &RegenerationRule)> {
+        [
+            (TypeCase::Energy, &self.energy),
+            (TypeCase::Ore, &self.ore),
+            (TypeCase::Science, &self.science),
+        ]
+        .into_iter()
+        .filter_map(|(resource, rule)| rule.as_ref().map(|rule| (resource, rule)))
+        .collect()
+    }
+}
+
+fn default_deposit_size() -> usize {
+    1
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct RobotsConfig {
     pub explorer: ExplorerConfig,
     pub collector: CollectorConfig,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ExplorerConfig {
     pub cost_science: usize,
     pub cost_ore: usize,
     pub cost_energy: usize,
     pub vision_range: usize,
     pub move_delay_ms: u64,
+    // Robots move forever without one
+    #[serde(default)]
+    pub battery: Option<BatteryConfig>,
+    // How the next move is chosen
+    #[serde(default)]
+    pub exploration: ExplorationMode,
+    // Grid of sectors shared out between the explorers of the frontier mode, none by default,
+    // refused with the random walk which does not use it
+    #[serde(default)]
+    pub sectors: Option<SectorsConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SectorsConfig {
+    pub columns: usize,
+    pub rows: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ExplorationMode {
+    // Random moves, three times more likely towards an unknown case
+    #[default]
+    RandomWalk,
+    // Straight to the closest known case next to the unknown, a different one per explorer
+    Frontier,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CollectorConfig {
     pub cost_science: usize,
     pub cost_ore: usize,
     pub cost_energy: usize,
     pub move_delay_ms: u64,
+    // Units carried back to the base in one trip
+    #[serde(default = "default_capacity")]
+    pub capacity: usize,
+    #[serde(default)]
+    pub battery: Option<BatteryConfig>,
+}
+
+fn default_capacity() -> usize {
+    1
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BatteryConfig {
+    pub capacity: usize,
+    pub drain_per_move: usize,
+    // Charge restored by one unit of the energy stock of the base
+    #[serde(default = "default_charge_per_energy")]
+    pub charge_per_energy: usize,
+}
+
+fn default_charge_per_energy() -> usize {
+    10
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct BaseConfig {
     pub initial_energy: usize,
     pub initial_ore: usize,
     pub initial_science: usize,
     pub initial_explorers: usize,
     pub initial_collectors: usize,
+    #[serde(default = "default_production_interval_ms")]
+    pub production_interval_ms: u64,
+    // Which robot is built at each production cycle
+    #[serde(default)]
+    pub production: ProductionConfig,
+    // Which resource a collector leaving the base goes for
+    #[serde(default)]
+    pub resource_selection: SelectionConfig,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum ProductionConfig {
+    // About two collectors per explorer, the original rule
+    #[default]
+    Ratio,
+    FixedRatio {
+        collectors_per_explorer: f64,
+    },
+    // Depends on the unknown tiles and on the known resources left to collect
+    Demand {
+        #[serde(default = "default_resources_per_collector")]
+        resources_per_collector: usize,
+    },
+    Nothing,
+}
+
+fn default_resources_per_collector() -> usize {
+    4
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum SelectionConfig {
+    // The resource furthest below the highest stock, then the closest as the crow flies
+    #[default]
+    StockPriority,
+    NearestPath,
+    CostWeighted,
+    RoundRobin,
+}
+
+fn default_production_interval_ms() -> u64 {
+    4000
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SimulationConfig {
+    // Duration of one simulation tick, every delay is rounded to a number of ticks
+    pub tick_ms: u64,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        SimulationConfig { tick_ms: 10 }
+    }
+}
+
+impl SimulationConfig {
+    pub fn ticks_for(&self, delay_ms: u64) -> u64 {
+        (delay_ms as f64 / self.tick_ms.max(1) as f64)
+            .round()
+            .max(1.0) as u64
+    }
 }
 
 impl Config {
-    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
-        let config_str = fs::read_to_string("config.toml")?;
+    pub fn load_from(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let config_str = fs::read_to_string(path)?;
         let config: Config = toml::from_str(&config_str)?;
+        config.check()?;
         Ok(config)
     }
+
+    // Settings that are read correctly but do not work together
+    pub(crate) fn check(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let pathfinding = &self.pathfinding;
+        if pathfinding.diagonal && matches!(pathfinding.heuristic, Some(HeuristicConfig::Manhattan))
+        {
+            return Err(
+                "[pathfinding] : l'heuristique manhattan surestime les diagonales, \
+                 utilisez octile avec diagonal = true"
+                    .into(),
+            );
+        }
+        let explorer = &self.robots.explorer;
+        if explorer.sectors.is_some() && explorer.exploration != ExplorationMode::Frontier {
+            return Err(
+                "[robots.explorer.sectors] : les secteurs ne servent qu'en exploration \
+                 frontier, ajoutez exploration = \"frontier\""
+                    .into(),
+            );
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MetricsConfig {
+    // Simulated time between two lines of the metrics file
+    pub interval_ms: u64,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        MetricsConfig { interval_ms: 1000 }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct PathfindingConfig {
+    // Moves to the 4 diagonal neighbours as well
+    pub diagonal: bool,
+    pub corner_cutting: CornerCutting,
+    // None picks the heuristic exact for the moves: octile with diagonals, manhattan without
+    pub heuristic: Option<HeuristicConfig>,
+    pub costs: TerrainCosts,
+}
+
+// When a diagonal move may pass next to a wall or an unknown case
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CornerCutting {
+    // Both cases beside the move must be passable
+    #[default]
+    Never,
+    // One case beside the move must be passable
+    OneSide,
+    Always,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum HeuristicConfig {
+    // Overestimates diagonal moves, paths may then not be the cheapest
+    Manhattan,
+    Octile,
+    Euclidean,
+    // No estimate, slower but exact whatever the moves
+    Dijkstra,
+}
+
+// Cost of moving onto each type of case, walls and unknown cases cannot be crossed
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct TerrainCosts {
+    pub void: u64,
+    pub base: u64,
+    pub energy: u64,
+    pub ore: u64,
+    pub science: u64,
+}
+
+impl Default for TerrainCosts {
+    fn default() -> Self {
+        TerrainCosts {
+            void: 1,
+            base: 1,
+            energy: 1,
+            ore: 1,
+            science: 1,
+        }
+    }
+}
+
+impl TerrainCosts {
+    pub fn cost(&self, case: &TypeCase) -> Option<u64> {
+        let cost = match case {
+            TypeCase::Wall | TypeCase::Unknown => return None,
+            TypeCase::Base => self.base,
+            TypeCase::Energy => self.energy,
+            TypeCase::Ore => self.ore,
+            TypeCase::Science => self.science,
+            TypeCase::Void | TypeCase::Explorer | TypeCase::Collector => self.void,
+        };
+        Some(cost.max(1))
+    }
+
+    // Cost of the cheapest case
+    pub fn min(&self) -> u64 {
+        [self.void, self.base, self.energy, self.ore, self.science]
+            .into_iter()
+            .min()
+            .unwrap_or(1)
+            .max(1)
+    }
 }
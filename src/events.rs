@@ -0,0 +1,107 @@
+use crate::generation::TypeCase;
+use serde::{Deserialize, Serialize};
+
+// Everything meaningful that happens during a tick, in the order it happened
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Event {
+    RobotSpawned {
+        robot: usize,
+        kind: TypeCase,
+        x: usize,
+        y: usize,
+    },
+    RobotMoved {
+        robot: usize,
+        x: usize,
+        y: usize,
+    },
+    // A case of the known map changed
+    TileRevealed {
+        x: usize,
+        y: usize,
+        case: TypeCase,
+    },
+    ResourceReserved {
+        x: usize,
+        y: usize,
+    },
+    ResourceReleased {
+        x: usize,
+        y: usize,
+    },
+    // No known path leads to the resource, it is skipped until the known map changes
+    ResourceUnreachable {
+        x: usize,
+        y: usize,
+    },
+    ResourceCollected {
+        robot: usize,
+        x: usize,
+        y: usize,
+        resource: TypeCase,
+        // Units taken from the deposit
+        amount: usize,
+    },
+    ResourceDelivered {
+        robot: usize,
+        resource: TypeCase,
+        amount: usize,
+    },
+    // A deposit grew back in the real map, the known map only changes once it is scouted again
+    ResourceSpawned {
+        x: usize,
+        y: usize,
+        resource: TypeCase,
+        amount: usize,
+    },
+    // A robot took energy from the stock of the base to refill its battery
+    RobotRecharged {
+        robot: usize,
+        energy: usize,
+    },
+    // A robot ran out of battery away from the base and waits for a collector
+    RobotStranded {
+        robot: usize,
+        x: usize,
+        y: usize,
+    },
+    RobotRescued {
+        robot: usize,
+        by: usize,
+    },
+    // The base paid for a new robot
+    ResourcesSpent {
+        energy: usize,
+        ore: usize,
+        science: usize,
+    },
+    PathComputed {
+        robot: usize,
+        from: (usize, usize),
+        to: (usize, usize),
+        length: usize,
+    },
+    PathFailed {
+        robot: usize,
+        from: (usize, usize),
+        to: (usize, usize),
+    },
+    // A case of the path of a robot cannot be walked on any more, the robot plans again
+    PathBlocked {
+        robot: usize,
+        x: usize,
+        y: usize,
+    },
+    // A collector gave up its destination, the resource ran out or no path leads there
+    DestinationAbandoned {
+        robot: usize,
+        x: usize,
+        y: usize,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TimedEvent {
+    pub tick: u64,
+    pub event: Event,
+}
@@ -1,11 +1,13 @@
 use noise::{NoiseFn, Perlin};
 use rand::rngs::StdRng;
 use rand::{Rng, SeedableRng};
-use std::sync::{Arc, Mutex};
+use serde::{Deserialize, Serialize};
 
-use crate::config::Config;
+use crate::config::{Config, Connectivity, Distribution, TerrainConfig};
+use crate::pathfinding::{self, neighbour, NEIGHBOURS};
+use std::collections::VecDeque;
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum TypeCase {
     Void,
     Base,
@@ -18,46 +20,169 @@ pub enum TypeCase {
     Unknown,
 }
 
-pub fn generate_map(
-    width: usize,
-    height: usize,
-    seed: u32,
-    config: &Config,
-) -> (
-    Arc<Mutex<Vec<Vec<TypeCase>>>>,
-    Arc<Mutex<Vec<Vec<TypeCase>>>>,
-    (usize, usize),
-) {
+// Random cases tried for the base before one is carved out of the terrain
+const BASE_ATTEMPTS: usize = 10_000;
+
+pub type Map = Vec<Vec<TypeCase>>;
+// Units left in the resource deposit of each case, 0 where there is no resource
+pub type Deposits = Vec<Vec<usize>>;
+
+// Everything a simulation starts from, generated or loaded from a map file
+pub struct World {
+    pub real_map: Map,
+    pub known_map: Map,
+    pub deposits: Deposits,
+    pub base: (usize, usize),
+}
+
+pub fn generate_map(width: usize, height: usize, seed: u32, config: &Config) -> World {
+    let terrain = &config.map.terrain;
     let perlin = Perlin::new(seed);
+    let biomes = config
+        .map
+        .biomes
+        .as_ref()
+        .map(|biomes| (Perlin::new(seed.wrapping_add(2)), biomes));
+    let biome_at = |x: usize, y: usize| {
+        biomes.map(|(noise, biomes)| {
+            let value = noise.get([x as f64 / biomes.scale, y as f64 / biomes.scale]);
+            match value {
+                v if v < -0.2 => &biomes.caves,
+                v if v > 0.2 => &biomes.crystal_fields,
+                _ => &biomes.plains,
+            }
+        })
+    };
     let mut rng = StdRng::seed_from_u64(seed as u64);
     let mut map = vec![vec![TypeCase::Void; width]; height];
-    let mut known_map = vec![vec![TypeCase::Unknown; width]; height];
+
+    // The first resource placed by the terrain owns the band of noise just above the walls
+    let band_resource = [
+        (TypeCase::Ore, &terrain.ore),
+        (TypeCase::Energy, &terrain.energy),
+        (TypeCase::Science, &terrain.science),
+    ]
+    .into_iter()
+    .find(|(_, distribution)| matches!(distribution, Distribution::Terrain))
+    .map(|(resource, _)| resource);
 
     // Generate the terrain
-    for y in 0..height {
-        for x in 0..width {
-            let noise_value = perlin.get([x as f64 / 9.25, y as f64 / 8.0]);
-            map[y][x] = match noise_value {
-                v if v < -0.55 => TypeCase::Wall,
-                v if v < -0.53 => TypeCase::Ore,
+    for (y, row) in map.iter_mut().enumerate() {
+        for (x, case) in row.iter_mut().enumerate() {
+            let noise_value = fbm(&perlin, x, y, terrain);
+            let (wall_threshold, band_threshold) = match biome_at(x, y) {
+                Some(biome) => {
+                    let wall_threshold = terrain.wall_threshold + biome.wall_offset;
+                    let band_width = terrain.band_threshold - terrain.wall_threshold;
+                    let density = band_resource
+                        .as_ref()
+                        .map_or(0.0, |resource| biome.density(resource));
+                    (wall_threshold, wall_threshold + band_width * density)
+                }
+                None => (terrain.wall_threshold, terrain.band_threshold),
+            };
+            *case = match (noise_value, &band_resource) {
+                (v, _) if v < wall_threshold => TypeCase::Wall,
+                (v, Some(resource)) if v < band_threshold => resource.clone(),
                 _ => TypeCase::Void,
             };
         }
     }
 
-    // Place the base
-    let mut base_x;
-    let mut base_y;
-    loop {
+    // Place the base on a void case, the terrain thresholds may leave none: the last case
+    // tried is then carved out for it
+    let mut base_x = 0;
+    let mut base_y = 0;
+    for _ in 0..BASE_ATTEMPTS {
         base_x = rng.random_range(0..width);
         base_y = rng.random_range(0..height);
         if map[base_y][base_x] == TypeCase::Void {
-            map[base_y][base_x] = TypeCase::Base;
             break;
         }
     }
+    map[base_y][base_x] = TypeCase::Base;
+
+    let mut known_map = reveal_base(&map, (base_x, base_y));
+
+    let map_size = width * height;
+    let count = (map_size as f64 * config.map.generation_rate).round() as usize;
 
-    // Reveal the area around the base in the known map
+    // Add the energy, science and ore points not placed by the terrain
+    let placements = [
+        (TypeCase::Energy, &terrain.energy, 3),
+        (TypeCase::Science, &terrain.science, 5),
+        (TypeCase::Ore, &terrain.ore, 4),
+    ];
+    for (resource, distribution, layer) in placements {
+        let clusters = Perlin::new(seed.wrapping_add(layer));
+        // Densest biome for this resource, where every candidate case is accepted
+        let max_density = config.map.biomes.as_ref().map(|biomes| {
+            [&biomes.caves, &biomes.plains, &biomes.crystal_fields]
+                .iter()
+                .map(|biome| biome.density(&resource))
+                .fold(0.0, f64::max)
+        });
+
+        let acceptance = |map: &Map, x: usize, y: usize| {
+            let placement = match distribution {
+                Distribution::Terrain => return 0.0,
+                Distribution::Uniform => 1.0,
+                Distribution::Clustered { scale, threshold } => {
+                    let value = clusters.get([x as f64 / scale, y as f64 / scale]);
+                    if value > *threshold {
+                        1.0
+                    } else {
+                        0.0
+                    }
+                }
+                Distribution::NearWalls { distance } => {
+                    if near_wall(map, x, y, *distance) {
+                        1.0
+                    } else {
+                        0.0
+                    }
+                }
+            };
+            match (biome_at(x, y), max_density) {
+                (Some(biome), Some(max_density)) if max_density > 0.0 => {
+                    placement * biome.density(&resource) / max_density
+                }
+                _ => placement,
+            }
+        };
+
+        if !matches!(distribution, Distribution::Terrain) {
+            place_resources(&mut map, &mut rng, &resource, count, acceptance);
+        }
+    }
+
+    let changed = match config.map.connectivity {
+        Connectivity::None => Vec::new(),
+        Connectivity::Carve => carve_tunnels(&mut map, (base_x, base_y)),
+        Connectivity::Relocate => relocate_resources(&mut map, (base_x, base_y), &mut rng),
+    };
+    // Keep the area revealed around the base in line with the real map
+    for (x, y) in changed {
+        if known_map[y][x] != TypeCase::Unknown {
+            known_map[y][x] = map[y][x].clone();
+        }
+    }
+
+    let deposits = generate_deposits(&map, seed, config);
+
+    World {
+        real_map: map,
+        known_map,
+        deposits,
+        base: (base_x, base_y),
+    }
+}
+
+// Known map at the start, only the area around the base is revealed
+pub fn reveal_base(map: &Map, (base_x, base_y): (usize, usize)) -> Map {
+    let height = map.len();
+    let width = map[0].len();
+    let mut known_map = vec![vec![TypeCase::Unknown; width]; height];
     for dy in -3..=3 {
         for dx in -3..=3 {
             let new_x = base_x as i32 + dx;
@@ -68,43 +193,192 @@ pub fn generate_map(
             }
         }
     }
+    known_map
+}
 
-    let map_size = width * height;
-    let percentage_spawn = config.map.generation_rate as f64;
+// Terrain noise, several octaves of Perlin noise added together and normalized
+fn fbm(perlin: &Perlin, x: usize, y: usize, terrain: &TerrainConfig) -> f64 {
+    let mut total = 0.0;
+    let mut amplitude = 1.0;
+    let mut frequency = 1.0;
+    let mut amplitudes = 0.0;
+    for _ in 0..terrain.octaves.max(1) {
+        let point = [
+            x as f64 / terrain.scale_x * frequency,
+            y as f64 / terrain.scale_y * frequency,
+        ];
+        total += perlin.get(point) * amplitude;
+        amplitudes += amplitude;
+        amplitude *= terrain.persistence;
+        frequency *= terrain.lacunarity;
+    }
+    total / amplitudes
+}
 
-    // Add energy points
-    let nb_energy = (map_size as f64 * percentage_spawn).round() as usize;
-    for _ in 0..nb_energy {
-        let mut x;
-        let mut y;
+// Put `count` cases of a resource on void cases drawn at random, each one kept with the
+// probability given by `acceptance`, gives up when the map has no room left
+fn place_resources(
+    map: &mut Map,
+    rng: &mut StdRng,
+    resource: &TypeCase,
+    count: usize,
+    acceptance: impl Fn(&Map, usize, usize) -> f64,
+) {
+    let height = map.len();
+    let width = map[0].len();
+    let mut attempts_left = (width * height).max(1000) * 100;
+    for _ in 0..count {
         loop {
-            x = rng.random_range(0..width);
-            y = rng.random_range(0..height);
-            if map[y][x] == TypeCase::Void {
-                map[y][x] = TypeCase::Energy;
+            if attempts_left == 0 {
+                return;
+            }
+            attempts_left -= 1;
+            let x = rng.random_range(0..width);
+            let y = rng.random_range(0..height);
+            if map[y][x] != TypeCase::Void {
+                continue;
+            }
+            let probability = acceptance(map, x, y);
+            if probability >= 1.0 || rng.random::<f64>() < probability {
+                map[y][x] = resource.clone();
                 break;
             }
         }
     }
+}
 
-    // Add science points
-    let nb_science = (map_size as f64 * percentage_spawn).round() as usize;
-    for _ in 0..nb_science {
-        let mut x;
-        let mut y;
-        loop {
-            x = rng.random_range(0..width);
-            y = rng.random_range(0..height);
-            if map[y][x] == TypeCase::Void {
-                map[y][x] = TypeCase::Science;
+// First resource no path from the base leads to, row by row
+fn isolated_resource(map: &Map, base: (usize, usize)) -> Option<(usize, usize)> {
+    let reached = pathfinding::distances_from(base, map);
+    map.iter().enumerate().find_map(|(y, row)| {
+        row.iter().enumerate().find_map(|(x, case)| {
+            let resource = matches!(case, TypeCase::Energy | TypeCase::Ore | TypeCase::Science);
+            (resource && reached[y][x].is_none()).then_some((x, y))
+        })
+    })
+}
+
+// Link every isolated resource to the cases reached from the base through the fewest walls,
+// returns the cases turned into void
+fn carve_tunnels(map: &mut Map, base: (usize, usize)) -> Vec<(usize, usize)> {
+    let height = map.len();
+    let width = map[0].len();
+    let mut carved = Vec::new();
+
+    while let Some(start) = isolated_resource(map, base) {
+        let reached = pathfinding::distances_from(base, map);
+
+        // 0-1 breadth-first search, crossing a wall costs 1 and any other case 0
+        let mut walls = vec![vec![usize::MAX; width]; height];
+        let mut previous = vec![vec![None; width]; height];
+        let mut queue = VecDeque::new();
+        walls[start.1][start.0] = 0;
+        queue.push_back(start);
+        let mut end = None;
+        while let Some((x, y)) = queue.pop_front() {
+            if reached[y][x].is_some() {
+                end = Some((x, y));
                 break;
             }
+            for (nx, ny) in NEIGHBOURS[..4]
+                .iter()
+                .filter_map(|&offset| neighbour(x, y, offset))
+            {
+                if nx >= width || ny >= height {
+                    continue;
+                }
+                let cost = usize::from(map[ny][nx] == TypeCase::Wall);
+                if walls[y][x] + cost < walls[ny][nx] {
+                    walls[ny][nx] = walls[y][x] + cost;
+                    previous[ny][nx] = Some((x, y));
+                    if cost == 0 {
+                        queue.push_front((nx, ny));
+                    } else {
+                        queue.push_back((nx, ny));
+                    }
+                }
+            }
+        }
+
+        let Some(mut current) = end else {
+            break;
+        };
+        while let Some((x, y)) = previous[current.1][current.0] {
+            if map[current.1][current.0] == TypeCase::Wall {
+                map[current.1][current.0] = TypeCase::Void;
+                carved.push(current);
+            }
+            current = (x, y);
+        }
+    }
+    carved
+}
+
+// Move every isolated resource to a random void case reached from the base, returns the cases
+// that changed
+fn relocate_resources(
+    map: &mut Map,
+    base: (usize, usize),
+    rng: &mut StdRng,
+) -> Vec<(usize, usize)> {
+    let reached = pathfinding::distances_from(base, map);
+    let mut free = Vec::new();
+    let mut isolated = Vec::new();
+    for (y, row) in map.iter().enumerate() {
+        for (x, case) in row.iter().enumerate() {
+            match case {
+                TypeCase::Void if reached[y][x].is_some() => free.push((x, y)),
+                TypeCase::Energy | TypeCase::Ore | TypeCase::Science if reached[y][x].is_none() => {
+                    isolated.push((x, y))
+                }
+                _ => {}
+            }
         }
     }
 
-    (
-        Arc::new(Mutex::new(map)),
-        Arc::new(Mutex::new(known_map)),
-        (base_x, base_y),
-    )
+    let mut changed = Vec::new();
+    for (x, y) in isolated {
+        let resource = std::mem::replace(&mut map[y][x], TypeCase::Void);
+        changed.push((x, y));
+        // Dropped when the area of the base is full
+        if free.is_empty() {
+            continue;
+        }
+        let (new_x, new_y) = free.swap_remove(rng.random_range(0..free.len()));
+        map[new_y][new_x] = resource;
+        changed.push((new_x, new_y));
+    }
+    changed
+}
+
+fn near_wall(map: &Map, x: usize, y: usize, distance: usize) -> bool {
+    let rows = y.saturating_sub(distance)..=(y + distance).min(map.len() - 1);
+    rows.into_iter().any(|ny| {
+        let columns = x.saturating_sub(distance)..=(x + distance).min(map[ny].len() - 1);
+        columns.into_iter().any(|nx| map[ny][nx] == TypeCase::Wall)
+    })
+}
+
+// Size every resource deposit between deposit_min and deposit_max from a second noise layer
+pub fn generate_deposits(map: &Map, seed: u32, config: &Config) -> Deposits {
+    let richness = Perlin::new(seed.wrapping_add(1));
+    let min = config.map.deposit_min.max(1);
+    let max = config.map.deposit_max.max(min);
+
+    map.iter()
+        .enumerate()
+        .map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .map(|(x, case)| match case {
+                    TypeCase::Energy | TypeCase::Ore | TypeCase::Science => {
+                        let noise_value = richness.get([x as f64 / 5.0, y as f64 / 5.0]);
+                        let intensity = ((noise_value + 1.0) / 2.0).clamp(0.0, 1.0);
+                        min + (intensity * (max - min) as f64).round() as usize
+                    }
+                    _ => 0,
+                })
+                .collect()
+        })
+        .collect()
 }
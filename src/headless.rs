@@ -0,0 +1,69 @@
+use crate::cli::{HeadlessOptions, Limit};
+use crate::output::Outputs;
+use rust_ereea::{save, ScenarioRun, Simulation};
+use std::error::Error;
+
+// Run the simulation without rendering until the limit is reached or the scenario ends, then
+// print a summary
+pub fn run(options: HeadlessOptions) -> Result<(), Box<dyn Error>> {
+    let mut simulation = options.world.build(None)?;
+    let mut outputs = Outputs::open(&options.output, &simulation)?;
+    let mut scenario = options
+        .world
+        .scenario()?
+        .map(|scenario| ScenarioRun::new(scenario, &simulation));
+
+    // The limit is relative to the starting tick so that a loaded game runs for as long
+    let duration = match options.limit {
+        Some(Limit::Ticks(ticks)) => ticks,
+        Some(Limit::DurationMs(duration_ms)) => {
+            duration_ms / simulation.tick_duration().as_millis().max(1) as u64
+        }
+        None => u64::MAX,
+    };
+    let last_tick = simulation.tick.saturating_add(duration);
+    while simulation.tick < last_tick {
+        simulation.step();
+        outputs.observe(&simulation)?;
+        if let Some(scenario) = &mut scenario {
+            if scenario.observe(&simulation).is_some() {
+                break;
+            }
+        }
+    }
+    outputs.flush()?;
+
+    print_summary(&simulation);
+    if let Some(scenario) = &scenario {
+        for line in scenario.report(&simulation) {
+            println!("{}", line);
+        }
+    }
+
+    if let Some(save_path) = &options.save_path {
+        save::save(&simulation, save_path)?;
+        println!("Sauvegardé dans {}", save_path);
+    }
+    Ok(())
+}
+
+fn print_summary(simulation: &Simulation) {
+    let base = &simulation.base;
+    let (explorers, collectors) = base.robot_counts();
+    let simulated_ms = simulation.tick * simulation.tick_duration().as_millis() as u64;
+
+    println!("Ticks : {} ({} ms simulées)", simulation.tick, simulated_ms);
+    println!(
+        "Ressources : {} énergie, {} minerai, {} science",
+        base.energy(),
+        base.ore(),
+        base.science()
+    );
+    println!(
+        "Robots : {} au total ({} explorateurs, {} collecteurs)",
+        explorers + collectors,
+        explorers,
+        collectors
+    );
+    println!("Exploré : {:.1}%", base.explored_ratio() * 100.0);
+}
@@ -0,0 +1,47 @@
+//! Simulation of a robot colony exploring a procedurally generated world.
+//!
+//! The binary is a terminal front end over this library (interface, command line, replays and
+//! output files), which can also be used on its own to generate worlds, step simulations and
+//! analyse them:
+//!
+//! ```no_run
+//! use rust_ereea::{Config, Simulation};
+//!
+//! let config = Config::load_from("config.toml").unwrap();
+//! let mut simulation = Simulation::new(60, 30, config);
+//! for _ in 0..1000 {
+//!     simulation.step();
+//! }
+//! println!("{} energy", simulation.base.energy());
+//! ```
+
+mod base;
+mod battery;
+pub mod config;
+mod events;
+mod generation;
+pub mod mapfile;
+mod metrics;
+mod pathfinding;
+mod production;
+mod regeneration;
+mod robot;
+pub mod save;
+mod scenario;
+mod sectors;
+mod selection;
+mod simulation;
+mod stats;
+
+pub use base::Base;
+pub use config::Config;
+pub use events::{Event, TimedEvent};
+pub use generation::{generate_map, Map, TypeCase, World};
+pub use metrics::Metrics;
+pub use pathfinding::{DistanceField, Heuristic, Pathfinder};
+pub use production::ProductionStrategy;
+pub use robot::{Collector, Explorer, Robot, RobotState, RobotStatus};
+pub use scenario::{Scenario, ScenarioRun};
+pub use selection::ResourceSelector;
+pub use simulation::{SimRng, Simulation};
+pub use stats::{History, Sample};
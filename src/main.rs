@@ -1,76 +1,207 @@
-mod base;
-mod config;
-mod generation;
-mod pathfinding;
-mod robot;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::terminal;
+
+mod camera;
+mod cli;
+mod headless;
+mod output;
+mod playback;
+mod replay;
 mod ui;
 
-use crossterm::terminal;
+use camera::Camera;
+use cli::{Command, ExportMapOptions, RunOptions};
+use output::Outputs;
+use playback::Playback;
+use rust_ereea::{mapfile, save, History, ScenarioRun, Simulation};
+use std::error::Error;
+use std::time::{Duration, Instant};
+use ui::{run_ui, Panels, Scene, Tui};
 
-use crate::base::Base;
-use crate::config::Config;
-use crate::generation::{generate_map, TypeCase};
-use crate::ui::run_ui;
-use std::sync::Arc;
+const FRAME_DURATION: Duration = Duration::from_millis(100);
 
 fn main() {
-    // Charger la configuration
-    let config = Config::load().expect("Impossible de charger la configuration");
-
-    let (width, height) = terminal::size().unwrap();
-    let width = (width / 2) as usize;
-    let height = height as usize;
-
-    // Utiliser la seed de la configuration
-    let seed = config.map.seed;
-
-    let (map, known_map, (base_x, base_y)) = generate_map(width, height, seed, &config);
-
-    // Créer la base avec la configuration
-    let base = Base::new(
-        width,
-        height,
-        base_x,
-        base_y,
-        map.clone(),
-        known_map.clone(),
-        config,
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command = match cli::parse_args(&args) {
+        Ok(command) => command,
+        Err(error) => {
+            eprintln!("{}\n\n{}", error, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    match command {
+        Command::Run(options) => run(options),
+        Command::Headless(options) => {
+            if let Err(error) = headless::run(options) {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+        }
+        Command::Replay(options) => {
+            if let Err(error) = replay::run(&options.path) {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+        }
+        Command::ExportMap(options) => {
+            if let Err(error) = export_map(&options) {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+// Write the real map of the world, generated, loaded or saved, without running it
+fn export_map(options: &ExportMapOptions) -> Result<(), Box<dyn Error>> {
+    let simulation = options.world.build(None)?;
+    mapfile::export(simulation.base.real_map(), &options.output_path)?;
+    println!(
+        "Carte {}x{} exportée dans {}",
+        simulation.base.width(),
+        simulation.base.height(),
+        options.output_path
     );
+    Ok(())
+}
+
+fn run(options: RunOptions) {
+    // The terminal size is only a fallback when the map size is not configured
+    let (terminal_width, terminal_height) = terminal::size().unwrap();
+    let fallback_size = ((terminal_width / 2) as usize, terminal_height as usize);
+
+    let mut simulation = match options.world.build(Some(fallback_size)) {
+        Ok(simulation) => simulation,
+        Err(error) => {
+            eprintln!("Impossible de créer la simulation : {}", error);
+            std::process::exit(1);
+        }
+    };
+
+    let mut scenario = match options.world.scenario() {
+        Ok(scenario) => scenario.map(|scenario| ScenarioRun::new(scenario, &simulation)),
+        Err(error) => {
+            eprintln!("Impossible de charger le scénario : {}", error);
+            std::process::exit(1);
+        }
+    };
 
-    Base::start_base_thread(Arc::clone(&base), width, height);
+    let mut terminal = ui::init_terminal().expect("Impossible d'initialiser le terminal");
+    let result = run_loop(&mut terminal, &mut simulation, &mut scenario, &options);
+    ui::restore_terminal(&mut terminal).expect("Impossible de restaurer le terminal");
+    if let Some(scenario) = &scenario {
+        for line in scenario.report(&simulation) {
+            println!("{}", line);
+        }
+    }
+    if let Err(error) = result {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    }
+}
+
+fn run_loop(
+    terminal: &mut Tui,
+    simulation: &mut Simulation,
+    scenario: &mut Option<ScenarioRun>,
+    options: &RunOptions,
+) -> Result<(), Box<dyn Error>> {
+    let save_path = &options.save_path;
+    let mut outputs = Outputs::open(&options.output, simulation)?;
+    let tick_duration = simulation.tick_duration();
+    let (base_x, base_y) = simulation.base.position();
+    let mut camera = Camera::new(base_x, base_y);
+    let mut playback = Playback::new();
+    let mut panels = Panels::new();
+    let mut history = History::new(simulation);
+    let mut status = String::new();
 
     // Garder le programme en vie
     loop {
-        if let Ok(base_guard) = base.lock() {
-            let energy = *base_guard.energy.lock().unwrap();
-            let ore = *base_guard.ore.lock().unwrap();
-            let science = *base_guard.science.lock().unwrap();
-            let nb_robots = base_guard.deployed_robots.lock().unwrap().len();
-
-            let mut nb_explorers = 0;
-            let mut nb_collectors = 0;
-            if let Ok(robots) = base_guard.deployed_robots.lock() {
-                for robot in robots.iter() {
-                    match robot.get_type() {
-                        TypeCase::Explorer => nb_explorers += 1,
-                        TypeCase::Collector => nb_collectors += 1,
-                        _ => {}
+        // Run as many ticks as the real time elapsed since the last frame, times the speed
+        // The simulation stops once the scenario has ended
+        for _ in 0..playback.due_ticks(tick_duration) {
+            if scenario.as_ref().is_some_and(|run| run.outcome().is_some()) {
+                break;
+            }
+            simulation.step();
+            history.observe(simulation);
+            outputs.observe(simulation)?;
+            if let Some(run) = scenario {
+                run.observe(simulation);
+            }
+        }
+        let objective = scenario.as_ref().map_or(String::new(), scenario_label);
+
+        let base = &simulation.base;
+        let scene = Scene::from_base(base);
+        camera.update(&scene);
+        let (nb_explorers, nb_collectors) = base.robot_counts();
+
+        let resources = format!(
+            "Tick {} {} | Ressources : {} énergie, {} minerai, {} science | Robots : {} au total ({} explorateurs, {} collecteurs){}{}",
+            simulation.tick,
+            playback.label(),
+            base.energy(),
+            base.ore(),
+            base.science(),
+            nb_explorers + nb_collectors,
+            nb_explorers,
+            nb_collectors,
+            objective,
+            status
+        );
+
+        run_ui(
+            terminal,
+            &scene,
+            &resources,
+            &camera,
+            &panels,
+            Some(&history),
+        )?;
+
+        // Wait for the next frame while staying responsive to the keyboard
+        let deadline = Instant::now() + FRAME_DURATION;
+        while let Some(timeout) = deadline.checked_duration_since(Instant::now()) {
+            if !event::poll(timeout)? {
+                break;
+            }
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                if ui::is_quit_key(&key) {
+                    return outputs.flush();
+                }
+                match key.code {
+                    KeyCode::Char('k') => {
+                        status = match save::save(simulation, save_path) {
+                            Ok(()) => format!(" | Sauvegardé dans {}", save_path),
+                            Err(error) => format!(" | Échec de la sauvegarde : {}", error),
+                        };
+                    }
+                    code => {
+                        if !playback.handle_key(code) && !panels.handle_key(code) {
+                            camera.handle_key(code, &Scene::from_base(&simulation.base));
+                        }
                     }
                 }
             }
-
-            let resources = format!(
-                "Resources: {} energy, {} ore, {} science | Robots: {} total ({} explorers, {} collectors)",
-                energy, ore, science, nb_robots, nb_explorers, nb_collectors
-            );
-
-            run_ui(
-                &base_guard.known_map,
-                &resources,
-                &base_guard.deployed_robots,
-            )
-            .unwrap();
         }
-        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+}
+
+// Result of the scenario once it has ended, else its name
+fn scenario_label(run: &ScenarioRun) -> String {
+    match run.outcome() {
+        Some(outcome) => format!(
+            " | {} ({}), score {}",
+            if outcome.won { "Réussite" } else { "Échec" },
+            outcome.reason,
+            outcome.score
+        ),
+        None => format!(" | {}", run.scenario().name),
     }
 }
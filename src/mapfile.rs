@@ -0,0 +1,375 @@
+use crate::config::Config;
+use crate::generation::{generate_deposits, reveal_base, Map, TypeCase, World};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+
+// Line closing the legend at the top of an ASCII map file
+const LEGEND_END: &str = "---";
+
+// Characters understood without a legend, also used when exporting
+const DEFAULT_LEGEND: [(char, TypeCase); 7] = [
+    ('.', TypeCase::Void),
+    (' ', TypeCase::Void),
+    ('#', TypeCase::Wall),
+    ('B', TypeCase::Base),
+    ('e', TypeCase::Energy),
+    ('o', TypeCase::Ore),
+    ('s', TypeCase::Science),
+];
+
+// Colour of each case in a PNG map, the same as the charts of the interface
+const PALETTE: [(TypeCase, [u8; 3]); 6] = [
+    (TypeCase::Void, [255, 255, 255]),
+    (TypeCase::Wall, [0, 0, 0]),
+    (TypeCase::Base, [255, 0, 0]),
+    (TypeCase::Energy, [255, 255, 0]),
+    (TypeCase::Ore, [0, 255, 255]),
+    (TypeCase::Science, [255, 0, 255]),
+];
+
+// PNG when the file ends with .png, ASCII grid otherwise
+fn is_png(path: &str) -> bool {
+    path.to_lowercase().ends_with(".png")
+}
+
+// Read a map file and build the world around the base it contains, the deposits are sized
+// from the seed like a generated map
+pub fn load(path: &str, config: &Config) -> Result<World, Box<dyn Error>> {
+    let map = if is_png(path) {
+        read_png(path)?
+    } else {
+        parse_ascii(&fs::read_to_string(path)?)?
+    };
+
+    let bases: Vec<(usize, usize)> = map
+        .iter()
+        .enumerate()
+        .flat_map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|(_, case)| **case == TypeCase::Base)
+                .map(move |(x, _)| (x, y))
+        })
+        .collect();
+    let base = match bases.as_slice() {
+        [base] => *base,
+        [] => return Err(format!("{} : la carte n'a pas de base", path).into()),
+        _ => return Err(format!("{} : la carte a {} bases", path, bases.len()).into()),
+    };
+
+    Ok(World {
+        known_map: reveal_base(&map, base),
+        deposits: generate_deposits(&map, config.map.seed, config),
+        real_map: map,
+        base,
+    })
+}
+
+// Write a map as an ASCII grid with the default legend, or as a PNG
+pub fn export(map: &Map, path: &str) -> Result<(), Box<dyn Error>> {
+    if is_png(path) {
+        write_png(map, path)
+    } else {
+        fs::write(path, to_ascii(map))?;
+        Ok(())
+    }
+}
+
+// An optional legend of `<character> = <case>` lines closed by `---`, then one line per row
+// of the map, shorter rows are filled with void
+pub fn parse_ascii(text: &str) -> Result<Map, Box<dyn Error>> {
+    let mut legend: HashMap<char, TypeCase> = DEFAULT_LEGEND.iter().cloned().collect();
+    let lines: Vec<&str> = text
+        .lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .collect();
+
+    let grid = match lines.iter().position(|line| line.trim() == LEGEND_END) {
+        Some(end) => {
+            for (number, line) in lines[..end].iter().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let (character, case) = parse_legend_line(line).ok_or_else(|| {
+                    format!("Ligne de légende {} invalide : {}", number + 1, line)
+                })?;
+                legend.insert(character, case);
+            }
+            &lines[end + 1..]
+        }
+        None => &lines[..],
+    };
+
+    // Blank lines after the last row are not part of the map
+    let rows = grid
+        .iter()
+        .rposition(|line| !line.trim().is_empty())
+        .map_or(0, |last| last + 1);
+    let grid = &grid[..rows];
+    let width = grid
+        .iter()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0);
+    if width == 0 {
+        return Err("La carte est vide".into());
+    }
+
+    grid.iter()
+        .enumerate()
+        .map(|(y, line)| {
+            let mut row = line
+                .chars()
+                .enumerate()
+                .map(|(x, character)| {
+                    legend.get(&character).cloned().ok_or_else(|| {
+                        format!(
+                            "Caractère '{}' inconnu ligne {}, colonne {}",
+                            character,
+                            y + 1,
+                            x + 1
+                        )
+                    })
+                })
+                .collect::<Result<Vec<TypeCase>, String>>()?;
+            row.resize(width, TypeCase::Void);
+            Ok(row)
+        })
+        .collect()
+}
+
+fn parse_legend_line(line: &str) -> Option<(char, TypeCase)> {
+    let (character, case) = line.split_once('=')?;
+    // The character may be a space, only the one before the `=` is kept
+    let mut characters = character.trim_start().chars();
+    let character = characters.next().unwrap_or(' ');
+    if !characters.as_str().trim().is_empty() {
+        return None;
+    }
+    let case = match case.trim().to_lowercase().as_str() {
+        "void" => TypeCase::Void,
+        "wall" => TypeCase::Wall,
+        "base" => TypeCase::Base,
+        "energy" => TypeCase::Energy,
+        "ore" => TypeCase::Ore,
+        "science" => TypeCase::Science,
+        _ => return None,
+    };
+    Some((character, case))
+}
+
+pub fn to_ascii(map: &Map) -> String {
+    let mut text = String::new();
+    for row in map {
+        for case in row {
+            let character = DEFAULT_LEGEND
+                .iter()
+                .find(|(_, legend_case)| legend_case == case)
+                .map_or('.', |(character, _)| *character);
+            text.push(character);
+        }
+        text.push('\n');
+    }
+    text
+}
+
+// Every pixel becomes the case of the closest colour of the palette, transparent pixels are void
+fn read_png(path: &str) -> Result<Map, Box<dyn Error>> {
+    let mut decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut buffer)?;
+    let pixels = &buffer[..frame.buffer_size()];
+
+    let channels = frame.color_type.samples();
+    let to_rgba = |pixel: &[u8]| match frame.color_type {
+        png::ColorType::Grayscale => [pixel[0], pixel[0], pixel[0], 255],
+        png::ColorType::GrayscaleAlpha => [pixel[0], pixel[0], pixel[0], pixel[1]],
+        png::ColorType::Rgba => [pixel[0], pixel[1], pixel[2], pixel[3]],
+        _ => [pixel[0], pixel[1], pixel[2], 255],
+    };
+
+    let width = frame.width as usize;
+    Ok(pixels
+        .chunks(frame.line_size)
+        .take(frame.height as usize)
+        .map(|line| {
+            line.chunks(channels)
+                .take(width)
+                .map(|pixel| match to_rgba(pixel) {
+                    [_, _, _, 0] => TypeCase::Void,
+                    [red, green, blue, _] => closest_case([red, green, blue]),
+                })
+                .collect()
+        })
+        .collect())
+}
+
+fn closest_case(colour: [u8; 3]) -> TypeCase {
+    let distance = |other: &[u8; 3]| -> u32 {
+        colour
+            .iter()
+            .zip(other)
+            .map(|(a, b)| (*a as i32 - *b as i32).pow(2) as u32)
+            .sum()
+    };
+    PALETTE
+        .iter()
+        .min_by_key(|(_, palette_colour)| distance(palette_colour))
+        .map_or(TypeCase::Void, |(case, _)| case.clone())
+}
+
+fn write_png(map: &Map, path: &str) -> Result<(), Box<dyn Error>> {
+    let height = map.len();
+    let width = map.first().map_or(0, Vec::len);
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(File::create(path)?),
+        width as u32,
+        height as u32,
+    );
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let pixels: Vec<u8> = map
+        .iter()
+        .flatten()
+        .flat_map(|case| {
+            PALETTE
+                .iter()
+                .find(|(palette_case, _)| palette_case == case)
+                .map_or([255, 255, 255], |(_, colour)| *colour)
+        })
+        .collect();
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&pixels)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation::generate_map;
+
+    fn config() -> Config {
+        Config::load_from(concat!(env!("CARGO_MANIFEST_DIR"), "/config.toml"))
+            .expect("the shipped configuration loads")
+    }
+
+    // File of the temporary directory, unique to the test process
+    fn temporary(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("ereea-{}-{}", std::process::id(), name));
+        path.to_str()
+            .expect("temporary path is valid UTF-8")
+            .to_string()
+    }
+
+    fn error<T>(result: Result<T, Box<dyn Error>>) -> String {
+        match result {
+            Ok(_) => panic!("the map is accepted"),
+            Err(error) => error.to_string(),
+        }
+    }
+
+    #[test]
+    fn default_legend_is_understood() {
+        let map = parse_ascii("B.#\ne o\ns\n").unwrap();
+        assert_eq!(
+            map,
+            vec![
+                vec![TypeCase::Base, TypeCase::Void, TypeCase::Wall],
+                vec![TypeCase::Energy, TypeCase::Void, TypeCase::Ore],
+                // Short rows are completed with void
+                vec![TypeCase::Science, TypeCase::Void, TypeCase::Void],
+            ]
+        );
+    }
+
+    #[test]
+    fn legend_adds_characters() {
+        let map = parse_ascii("~ = wall\n* = Energy\n\n---\nB~*\n").unwrap();
+        assert_eq!(
+            map,
+            vec![vec![TypeCase::Base, TypeCase::Wall, TypeCase::Energy]]
+        );
+    }
+
+    #[test]
+    fn unknown_characters_are_refused() {
+        assert_eq!(
+            error(parse_ascii("B..\n.x.\n")),
+            "Caractère 'x' inconnu ligne 2, colonne 2"
+        );
+        assert_eq!(
+            error(parse_ascii("~ = lava\n---\nB~\n")),
+            "Ligne de légende 1 invalide : ~ = lava"
+        );
+        assert_eq!(error(parse_ascii("\n\n")), "La carte est vide");
+    }
+
+    #[test]
+    fn maps_need_a_single_base() {
+        let config = config();
+        let path = temporary("bases.txt");
+        fs::write(&path, "...\n.#.\n").unwrap();
+        let missing = error(load(&path, &config));
+        fs::write(&path, "B..\n.#B\n").unwrap();
+        let duplicate = error(load(&path, &config));
+        fs::write(&path, "...\n.#B\n").unwrap();
+        let single = load(&path, &config);
+        fs::remove_file(&path).ok();
+
+        assert!(missing.ends_with("la carte n'a pas de base"), "{}", missing);
+        assert!(duplicate.ends_with("la carte a 2 bases"), "{}", duplicate);
+        assert_eq!(single.unwrap().base, (2, 1));
+    }
+
+    #[test]
+    fn png_colours_become_the_closest_case() {
+        let pixels: [[u8; 4]; 6] = [
+            [250, 250, 250, 255],
+            [20, 10, 10, 255],
+            [230, 30, 20, 255],
+            [240, 230, 40, 255],
+            [0, 0, 0, 0],
+            [10, 220, 240, 255],
+        ];
+        let path = temporary("palette.png");
+        let mut encoder = png::Encoder::new(BufWriter::new(File::create(&path).unwrap()), 3, 2);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(pixels.as_flattened()).unwrap();
+        writer.finish().unwrap();
+        let map = read_png(&path);
+        fs::remove_file(&path).ok();
+
+        assert_eq!(
+            map.unwrap(),
+            vec![
+                vec![TypeCase::Void, TypeCase::Wall, TypeCase::Base],
+                // Transparent pixels are void whatever their colour
+                vec![TypeCase::Energy, TypeCase::Void, TypeCase::Ore],
+            ]
+        );
+    }
+
+    #[test]
+    fn exported_maps_load_back() {
+        let config = config();
+        let world = generate_map(40, 20, config.map.seed, &config);
+        for name in ["world.txt", "world.png"] {
+            let path = temporary(name);
+            export(&world.real_map, &path).unwrap();
+            let loaded = load(&path, &config);
+            fs::remove_file(&path).ok();
+
+            let loaded = loaded.unwrap();
+            assert_eq!(loaded.real_map, world.real_map, "{}", name);
+            assert_eq!(loaded.base, world.base, "{}", name);
+            assert_eq!(loaded.deposits, world.deposits, "{}", name);
+        }
+    }
+}
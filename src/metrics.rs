@@ -0,0 +1,233 @@
+use crate::events::Event;
+use crate::generation::TypeCase;
+use crate::robot::RobotState;
+use crate::simulation::Simulation;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+// One line of the metrics file, counters are for the interval unless stated otherwise
+#[derive(Debug, Serialize)]
+pub struct MetricsRow {
+    pub tick: u64,
+    pub time_ms: u64,
+    // Stock of the base
+    pub energy: usize,
+    pub ore: usize,
+    pub science: usize,
+    pub delivered_energy: usize,
+    pub delivered_ore: usize,
+    pub delivered_science: usize,
+    pub explorers: usize,
+    pub collectors: usize,
+    // Share of the known map revealed, between 0 and 1
+    pub explored: f64,
+    // Collector round trips finished, and their average duration in ticks
+    pub trips: usize,
+    pub average_trip_ticks: Option<f64>,
+    // Share of the collector ticks spent idle at the base
+    pub collector_idle_ratio: f64,
+    pub path_failures: usize,
+    // Energy of the stock spent on batteries, and robots waiting for a rescue at the end
+    pub recharge_energy: usize,
+    pub stranded: usize,
+}
+
+const CSV_HEADER: &str = "tick,time_ms,energy,ore,science,delivered_energy,delivered_ore,\
+delivered_science,explorers,collectors,explored,trips,average_trip_ticks,\
+collector_idle_ratio,path_failures,recharge_energy,stranded";
+
+impl MetricsRow {
+    fn to_csv(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{:.4},{},{},{:.4},{},{},{}",
+            self.tick,
+            self.time_ms,
+            self.energy,
+            self.ore,
+            self.science,
+            self.delivered_energy,
+            self.delivered_ore,
+            self.delivered_science,
+            self.explorers,
+            self.collectors,
+            self.explored,
+            self.trips,
+            self.average_trip_ticks
+                .map_or(String::new(), |ticks| format!("{:.1}", ticks)),
+            self.collector_idle_ratio,
+            self.path_failures,
+            self.recharge_energy,
+            self.stranded
+        )
+    }
+}
+
+pub enum MetricsFormat {
+    Csv,
+    JsonLines,
+}
+
+impl MetricsFormat {
+    // Chosen from the extension of the file, JSON lines unless it ends with .csv
+    pub fn from_path(path: &str) -> Self {
+        if path.to_lowercase().ends_with(".csv") {
+            MetricsFormat::Csv
+        } else {
+            MetricsFormat::JsonLines
+        }
+    }
+}
+
+// Samples the simulation every `interval_ms` of simulated time and writes one row each time
+pub struct Metrics {
+    writer: BufWriter<File>,
+    format: MetricsFormat,
+    tick_ms: u64,
+    interval_ticks: u64,
+    // Energy, ore and science delivered during the interval
+    delivered: (usize, usize, usize),
+    trip_starts: HashMap<usize, u64>,
+    trip_ticks: Vec<u64>,
+    idle_ticks: usize,
+    collector_ticks: usize,
+    path_failures: usize,
+    recharge_energy: usize,
+}
+
+impl Metrics {
+    pub fn create(path: &str, simulation: &Simulation) -> Result<Self, Box<dyn Error>> {
+        let tick_ms = simulation.tick_duration().as_millis().max(1) as u64;
+        let config = simulation.base.get_config();
+        let interval_ticks = config.simulation.ticks_for(config.metrics.interval_ms);
+        let format = MetricsFormat::from_path(path);
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        if let MetricsFormat::Csv = format {
+            writeln!(writer, "{}", CSV_HEADER)?;
+        }
+
+        Ok(Metrics {
+            writer,
+            format,
+            tick_ms,
+            interval_ticks,
+            delivered: (0, 0, 0),
+            trip_starts: HashMap::new(),
+            trip_ticks: Vec::new(),
+            idle_ticks: 0,
+            collector_ticks: 0,
+            path_failures: 0,
+            recharge_energy: 0,
+        })
+    }
+
+    // Account for the last step and write a row at the end of each interval
+    pub fn observe(&mut self, simulation: &Simulation) -> Result<(), Box<dyn Error>> {
+        for timed in simulation.events() {
+            match &timed.event {
+                Event::ResourceDelivered {
+                    robot,
+                    resource,
+                    amount,
+                } => {
+                    match resource {
+                        TypeCase::Energy => self.delivered.0 += amount,
+                        TypeCase::Ore => self.delivered.1 += amount,
+                        TypeCase::Science => self.delivered.2 += amount,
+                        _ => {}
+                    }
+                    if let Some(start) = self.trip_starts.remove(robot) {
+                        self.trip_ticks.push(timed.tick - start);
+                    }
+                }
+                // A trip starts when a collector leaves the base
+                Event::PathComputed { robot, from, .. }
+                    if *from == (simulation.base.position_x, simulation.base.position_y) =>
+                {
+                    self.trip_starts.insert(*robot, timed.tick);
+                }
+                // Rescues are not collection trips
+                Event::RobotRescued { by, .. } => {
+                    self.trip_starts.remove(by);
+                }
+                Event::RobotRecharged { energy, .. } => self.recharge_energy += energy,
+                Event::PathFailed { robot, .. } => {
+                    self.trip_starts.remove(robot);
+                    self.path_failures += 1;
+                }
+                _ => {}
+            }
+        }
+
+        let mut stranded = 0;
+        for robot in simulation.base.deployed_robots.iter() {
+            if robot.get_state() == RobotState::Stranded {
+                stranded += 1;
+            }
+            if robot.get_type() == TypeCase::Collector {
+                self.collector_ticks += 1;
+                if robot.get_state() == RobotState::Idle {
+                    self.idle_ticks += 1;
+                }
+            }
+        }
+
+        if simulation.tick.is_multiple_of(self.interval_ticks) {
+            let row = self.take_row(simulation, stranded);
+            match self.format {
+                MetricsFormat::Csv => writeln!(self.writer, "{}", row.to_csv())?,
+                MetricsFormat::JsonLines => {
+                    serde_json::to_writer(&mut self.writer, &row)?;
+                    writeln!(self.writer)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    // Build the row of the interval that just ended and reset the interval counters
+    fn take_row(&mut self, simulation: &Simulation, stranded: usize) -> MetricsRow {
+        let base = &simulation.base;
+        let (explorers, collectors) = base.robot_counts();
+        let delivered = std::mem::take(&mut self.delivered);
+        let trip_ticks = std::mem::take(&mut self.trip_ticks);
+        let idle_ticks = std::mem::take(&mut self.idle_ticks);
+        let collector_ticks = std::mem::take(&mut self.collector_ticks);
+
+        MetricsRow {
+            tick: simulation.tick,
+            time_ms: simulation.tick * self.tick_ms,
+            energy: base.energy,
+            ore: base.ore,
+            science: base.science,
+            delivered_energy: delivered.0,
+            delivered_ore: delivered.1,
+            delivered_science: delivered.2,
+            explorers,
+            collectors,
+            explored: base.explored_ratio(),
+            trips: trip_ticks.len(),
+            average_trip_ticks: if trip_ticks.is_empty() {
+                None
+            } else {
+                Some(trip_ticks.iter().sum::<u64>() as f64 / trip_ticks.len() as f64)
+            },
+            collector_idle_ratio: if collector_ticks == 0 {
+                0.0
+            } else {
+                idle_ticks as f64 / collector_ticks as f64
+            },
+            path_failures: std::mem::take(&mut self.path_failures),
+            recharge_energy: std::mem::take(&mut self.recharge_energy),
+            stranded,
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use crate::cli::OutputOptions;
+use crate::replay::Recorder;
+use rust_ereea::{Metrics, Simulation};
+use std::error::Error;
+
+// Files written alongside a running simulation, fed after every step
+pub struct Outputs {
+    recorder: Option<Recorder>,
+    metrics: Option<Metrics>,
+}
+
+impl Outputs {
+    pub fn open(options: &OutputOptions, simulation: &Simulation) -> Result<Self, Box<dyn Error>> {
+        let recorder = match &options.record_path {
+            Some(path) => Some(Recorder::create(path, simulation)?),
+            None => None,
+        };
+        let metrics = match &options.metrics_path {
+            Some(path) => Some(Metrics::create(path, simulation)?),
+            None => None,
+        };
+        Ok(Outputs { recorder, metrics })
+    }
+
+    pub fn observe(&mut self, simulation: &Simulation) -> Result<(), Box<dyn Error>> {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record(simulation.events())?;
+        }
+        if let Some(metrics) = &mut self.metrics {
+            metrics.observe(simulation)?;
+        }
+        Ok(())
+    }
+
+    // Write what is still buffered, so that errors are reported instead of lost on drop
+    pub fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.flush()?;
+        }
+        if let Some(metrics) = &mut self.metrics {
+            metrics.flush()?;
+        }
+        Ok(())
+    }
+}
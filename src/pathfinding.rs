@@ -1,33 +1,776 @@
+use crate::config::{CornerCutting, HeuristicConfig, PathfindingConfig, TerrainCosts};
 use crate::generation::TypeCase;
-use seastar::{astar, Grid, Point};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
 
-#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
-pub struct Pos(pub usize, pub usize);
+// Cost of a straight and of a diagonal move onto a case of cost 1, diagonals are about √2 longer
+const STRAIGHT: u64 = 10;
+const DIAGONAL: u64 = 14;
 
-pub fn find_path(
-    start: (usize, usize),
-    goal: (usize, usize),
-    map: &Vec<Vec<TypeCase>>,
-) -> Option<Vec<(usize, usize)>> {
-    let width = map[0].len();
+// Offsets of the cases around another, the four straight moves first then the four diagonals
+pub const NEIGHBOURS: [(isize, isize); 8] = [
+    (-1, 0),
+    (1, 0),
+    (0, -1),
+    (0, 1),
+    (-1, -1),
+    (1, -1),
+    (-1, 1),
+    (1, 1),
+];
+
+// Case at `offset` from (x, y), None past the left or top edge of the map
+pub fn neighbour(x: usize, y: usize, (dx, dy): (isize, isize)) -> Option<(usize, usize)> {
+    Some((x.checked_add_signed(dx)?, y.checked_add_signed(dy)?))
+}
+
+// Estimate of the cost left from a case to the goal, in STRAIGHT units for cases of cost 1
+pub trait Heuristic {
+    fn estimate(&self, from: (usize, usize), to: (usize, usize)) -> u64;
+}
+
+// Exact for 4-directional moves. A diagonal move counts as two straight ones, so with diagonals
+// it overestimates and the paths found may not be the cheapest: the configuration refuses it
+pub struct Manhattan;
+
+impl Heuristic for Manhattan {
+    fn estimate(&self, from: (usize, usize), to: (usize, usize)) -> u64 {
+        let (dx, dy) = offsets(from, to);
+        (dx + dy) * STRAIGHT
+    }
+}
+
+// Exact for 8-directional moves
+pub struct Octile;
+
+impl Heuristic for Octile {
+    fn estimate(&self, from: (usize, usize), to: (usize, usize)) -> u64 {
+        let (dx, dy) = offsets(from, to);
+        dx.max(dy) * STRAIGHT + dx.min(dy) * (DIAGONAL - STRAIGHT)
+    }
+}
+
+// Straight line, scaled down to DIAGONAL / √2 per case so that it never exceeds the cost of
+// the diagonal moves, rounded to 14 instead of 10√2
+pub struct Euclidean;
+
+impl Heuristic for Euclidean {
+    fn estimate(&self, from: (usize, usize), to: (usize, usize)) -> u64 {
+        let (dx, dy) = offsets(from, to);
+        let length = ((dx * dx + dy * dy) as f64).sqrt();
+        (length * DIAGONAL as f64 / std::f64::consts::SQRT_2) as u64
+    }
+}
+
+// No estimate, the search becomes Dijkstra's algorithm
+pub struct Dijkstra;
+
+impl Heuristic for Dijkstra {
+    fn estimate(&self, _from: (usize, usize), _to: (usize, usize)) -> u64 {
+        0
+    }
+}
+
+// Best known cost of a visited case and the case it was reached from
+type Visit = (u64, Option<(usize, usize)>);
+
+fn offsets(from: (usize, usize), to: (usize, usize)) -> (u64, u64) {
+    (from.0.abs_diff(to.0) as u64, from.1.abs_diff(to.1) as u64)
+}
+
+pub fn heuristic_from_config(config: &HeuristicConfig) -> Box<dyn Heuristic> {
+    match config {
+        HeuristicConfig::Manhattan => Box::new(Manhattan),
+        HeuristicConfig::Octile => Box::new(Octile),
+        HeuristicConfig::Euclidean => Box::new(Euclidean),
+        HeuristicConfig::Dijkstra => Box::new(Dijkstra),
+    }
+}
+
+// A* search over the map itself, with a movement cost for each type of case
+pub struct Pathfinder {
+    pub costs: TerrainCosts,
+    pub diagonal: bool,
+    pub corner_cutting: CornerCutting,
+    pub heuristic: Box<dyn Heuristic>,
+}
+
+impl Default for Pathfinder {
+    fn default() -> Self {
+        Self::from_config(&PathfindingConfig::default())
+    }
+}
+
+impl Pathfinder {
+    pub fn from_config(config: &PathfindingConfig) -> Self {
+        Pathfinder {
+            costs: config.costs.clone(),
+            diagonal: config.diagonal,
+            corner_cutting: config.corner_cutting,
+            heuristic: match &config.heuristic {
+                Some(heuristic) => heuristic_from_config(heuristic),
+                None if config.diagonal => Box::new(Octile),
+                None => Box::new(Manhattan),
+            },
+        }
+    }
+
+    // Cheapest path from `start` to `goal`, both included, None when walls or unknown cases
+    // block every way
+    pub fn find_path(
+        &self,
+        start: (usize, usize),
+        goal: (usize, usize),
+        map: &[Vec<TypeCase>],
+    ) -> Option<Vec<(usize, usize)>> {
+        let passable = |case: (usize, usize)| self.case_cost(map, case).is_some();
+        if !passable(goal) || map.get(start.1).and_then(|row| row.get(start.0)).is_none() {
+            return None;
+        }
+        // The estimate is scaled by the cheapest case so that it never overestimates
+        let estimate =
+            |case: (usize, usize)| self.heuristic.estimate(case, goal) * self.costs.min();
+
+        let mut visited: HashMap<(usize, usize), Visit> = HashMap::new();
+        let mut open = BinaryHeap::new();
+        visited.insert(start, (0, None));
+        open.push(Reverse((estimate(start), 0, start.1, start.0)));
+
+        while let Some(Reverse((_, cost, y, x))) = open.pop() {
+            if (x, y) == goal {
+                let mut path = vec![goal];
+                let mut current = goal;
+                while let Some((_, Some(previous))) = visited.get(&current) {
+                    path.push(*previous);
+                    current = *previous;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            // Outdated entry, the case was reached more cheaply since
+            if visited.get(&(x, y)).is_some_and(|&(best, _)| best < cost) {
+                continue;
+            }
+
+            for (nx, ny, diagonal) in self.neighbours(x, y, &passable) {
+                let Some(case_cost) = self.costs.cost(&map[ny][nx]) else {
+                    continue;
+                };
+                let step = if diagonal { DIAGONAL } else { STRAIGHT };
+                let next_cost = cost + step * case_cost;
+                if visited
+                    .get(&(nx, ny))
+                    .is_some_and(|&(best, _)| best <= next_cost)
+                {
+                    continue;
+                }
+                visited.insert((nx, ny), (next_cost, Some((x, y))));
+                open.push(Reverse((next_cost + estimate((nx, ny)), next_cost, ny, nx)));
+            }
+        }
+        None
+    }
+
+    // Cost of moving onto a case of the map, None outside of it or when it cannot be walked on
+    fn case_cost(&self, map: &[Vec<TypeCase>], (x, y): (usize, usize)) -> Option<u64> {
+        map.get(y)
+            .and_then(|row| row.get(x))
+            .and_then(|case| self.costs.cost(case))
+    }
+
+    // Passable cases one move away, with whether the move is diagonal
+    fn neighbours(
+        &self,
+        x: usize,
+        y: usize,
+        passable: &impl Fn((usize, usize)) -> bool,
+    ) -> Vec<(usize, usize, bool)> {
+        let mut neighbours = Vec::with_capacity(8);
+        let moves = if self.diagonal { 8 } else { 4 };
+        for &offset in &NEIGHBOURS[..moves] {
+            let Some((nx, ny)) = neighbour(x, y, offset) else {
+                continue;
+            };
+            if !passable((nx, ny)) {
+                continue;
+            }
+            let diagonal = offset.0 != 0 && offset.1 != 0;
+            if diagonal {
+                // The two cases beside the move, around the corner being cut
+                let sides = [passable((nx, y)), passable((x, ny))];
+                let allowed = match self.corner_cutting {
+                    CornerCutting::Never => sides.iter().all(|side| *side),
+                    CornerCutting::OneSide => sides.iter().any(|side| *side),
+                    CornerCutting::Always => true,
+                };
+                if !allowed {
+                    continue;
+                }
+            }
+            neighbours.push((nx, ny, diagonal));
+        }
+        neighbours
+    }
+}
+
+// Cheapest way from a case to the root of a distance field: its cost, its number of moves and
+// the next case on it
+#[derive(Debug, Clone, Copy)]
+struct Way {
+    cost: u64,
+    moves: usize,
+    next: Option<(usize, usize)>,
+}
+
+impl Way {
+    // Among ways of the same cost the one with the fewest moves then the smallest next case
+    // wins, so that a field updated case by case keeps the same ways as a new one
+    fn key(&self) -> (u64, usize, Option<(usize, usize)>) {
+        (self.cost, self.moves, self.next)
+    }
+
+    fn better_than(&self, other: Option<Way>) -> bool {
+        other.is_none_or(|other| self.key() < other.key())
+    }
+}
+
+// Cheapest way from every case of a map to a single root with the movement rules of a
+// pathfinder, kept up to date case by case as the map changes. Following the next case of each
+// way gives the path to the root without any search
+#[derive(Debug, Clone)]
+pub struct DistanceField {
+    root: (usize, usize),
+    ways: Vec<Vec<Option<Way>>>,
+}
+
+impl DistanceField {
+    pub fn new(root: (usize, usize), map: &[Vec<TypeCase>], pathfinder: &Pathfinder) -> Self {
+        let width = map.first().map_or(0, |row| row.len());
+        let mut field = DistanceField {
+            root,
+            ways: vec![vec![None; width]; map.len()],
+        };
+        let mut open = BinaryHeap::new();
+        if pathfinder.case_cost(map, root).is_some() {
+            field.ways[root.1][root.0] = Some(Way {
+                cost: 0,
+                moves: 0,
+                next: None,
+            });
+            open.push(Reverse((0, root.1, root.0)));
+        }
+        field.spread(open, map, pathfinder);
+        field
+    }
+
+    // Account for the case at (x, y) having been `previous` before the map changed, only the
+    // cases whose way goes through it are computed again
+    pub fn update(
+        &mut self,
+        x: usize,
+        y: usize,
+        previous: &TypeCase,
+        map: &[Vec<TypeCase>],
+        pathfinder: &Pathfinder,
+    ) {
+        if y >= self.ways.len() || x >= self.ways[y].len() {
+            return;
+        }
+        let cost = pathfinder.case_cost(map, (x, y));
+        if cost == pathfinder.costs.cost(previous) {
+            return;
+        }
+        if (x, y) == self.root {
+            *self = DistanceField::new(self.root, map, pathfinder);
+            return;
+        }
+
+        // The first move of these ways is not valid or not the same price any more: moving
+        // onto the case costs something else, and when the case cannot be walked on it has no
+        // way and the diagonals around it may not cut its corner
+        let mut around = vec![(x, y)];
+        for (nx, ny) in NEIGHBOURS
+            .iter()
+            .filter_map(|&offset| neighbour(x, y, offset))
+        {
+            if ny < self.ways.len() && nx < self.ways[ny].len() {
+                around.push((nx, ny));
+            }
+        }
+        let broken: Vec<(usize, usize)> = around
+            .iter()
+            .copied()
+            .filter(|&(nx, ny)| {
+                let Some(next) = self.next_step(nx, ny) else {
+                    return (nx, ny) == (x, y) && cost.is_none() && self.ways[y][x].is_some();
+                };
+                let cuts_corner = next.0 != nx && next.1 != ny && {
+                    let sides = [(next.0, ny), (nx, next.1)];
+                    sides.contains(&(x, y))
+                };
+                next == (x, y) || (cost.is_none() && ((nx, ny) == (x, y) || cuts_corner))
+            })
+            .collect();
+        let lost = self.ways_through(&broken);
+        for &(lx, ly) in &lost {
+            self.ways[ly][lx] = None;
+        }
+
+        // The cases left without a way start again from their neighbours, then the changed
+        // case and the cases around it offer their way to their neighbours, through the new
+        // cost or the diagonals the case no longer blocks
+        let mut open = BinaryHeap::new();
+        for (lx, ly) in lost.into_iter().chain(around) {
+            if self.ways[ly][lx].is_none() {
+                self.ways[ly][lx] = self.best_neighbour(lx, ly, map, pathfinder);
+            }
+            if let Some(way) = self.ways[ly][lx] {
+                open.push(Reverse((way.cost, ly, lx)));
+            }
+        }
+        self.spread(open, map, pathfinder);
+    }
+
+    pub fn cost(&self, x: usize, y: usize) -> Option<u64> {
+        self.way(x, y).map(|way| way.cost)
+    }
+
+    // Number of moves of the cheapest way to the root, None when there is none
+    pub fn moves(&self, x: usize, y: usize) -> Option<usize> {
+        self.way(x, y).map(|way| way.moves)
+    }
+
+    // Case to move to from (x, y) to get closer to the root
+    pub fn next_step(&self, x: usize, y: usize) -> Option<(usize, usize)> {
+        self.way(x, y).and_then(|way| way.next)
+    }
+
+    // Cheapest path from (x, y) to the root, both included
+    pub fn path(&self, x: usize, y: usize) -> Option<Vec<(usize, usize)>> {
+        let way = self.way(x, y)?;
+        let mut path = Vec::with_capacity(way.moves + 1);
+        path.push((x, y));
+        let mut current = (x, y);
+        while let Some(next) = self.next_step(current.0, current.1) {
+            path.push(next);
+            current = next;
+        }
+        Some(path)
+    }
+
+    fn way(&self, x: usize, y: usize) -> Option<Way> {
+        self.ways
+            .get(y)
+            .and_then(|row| row.get(x))
+            .copied()
+            .flatten()
+    }
+
+    // Dijkstra's algorithm from the cases of `open`, every case reached more cheaply than before
+    // takes the way through the case it was reached from
+    fn spread(
+        &mut self,
+        mut open: BinaryHeap<Reverse<(u64, usize, usize)>>,
+        map: &[Vec<TypeCase>],
+        pathfinder: &Pathfinder,
+    ) {
+        let passable = |case: (usize, usize)| pathfinder.case_cost(map, case).is_some();
+        while let Some(Reverse((cost, y, x))) = open.pop() {
+            let Some(way) = self.ways[y][x] else {
+                continue;
+            };
+            // Outdated entry, the case was reached more cheaply since
+            if way.cost < cost {
+                continue;
+            }
+            let Some(case_cost) = pathfinder.case_cost(map, (x, y)) else {
+                continue;
+            };
+            // The neighbours move onto this case, so they pay its cost
+            for (nx, ny, diagonal) in pathfinder.neighbours(x, y, &passable) {
+                let step = if diagonal { DIAGONAL } else { STRAIGHT };
+                let next_cost = cost + step * case_cost;
+                let candidate = Way {
+                    cost: next_cost,
+                    moves: way.moves + 1,
+                    next: Some((x, y)),
+                };
+                if !candidate.better_than(self.ways[ny][nx]) {
+                    continue;
+                }
+                self.ways[ny][nx] = Some(candidate);
+                open.push(Reverse((next_cost, ny, nx)));
+            }
+        }
+    }
+
+    // Cheapest way through one of the neighbours that still have one
+    fn best_neighbour(
+        &self,
+        x: usize,
+        y: usize,
+        map: &[Vec<TypeCase>],
+        pathfinder: &Pathfinder,
+    ) -> Option<Way> {
+        let passable = |case: (usize, usize)| pathfinder.case_cost(map, case).is_some();
+        if !passable((x, y)) {
+            return None;
+        }
+        let mut best: Option<Way> = None;
+        for (nx, ny, diagonal) in pathfinder.neighbours(x, y, &passable) {
+            let (Some(way), Some(case_cost)) =
+                (self.ways[ny][nx], pathfinder.case_cost(map, (nx, ny)))
+            else {
+                continue;
+            };
+            let step = if diagonal { DIAGONAL } else { STRAIGHT };
+            let candidate = Way {
+                cost: way.cost + step * case_cost,
+                moves: way.moves + 1,
+                next: Some((nx, ny)),
+            };
+            if candidate.better_than(best) {
+                best = Some(candidate);
+            }
+        }
+        best
+    }
+
+    // Cases whose way to the root goes through one of `cases`, them included
+    fn ways_through(&self, cases: &[(usize, usize)]) -> Vec<(usize, usize)> {
+        if cases.is_empty() {
+            return Vec::new();
+        }
+        // Whether the way of each case goes through one of `cases`, once known
+        let mut through: Vec<Vec<Option<bool>>> =
+            self.ways.iter().map(|row| vec![None; row.len()]).collect();
+        let mut found = Vec::new();
+        for &(x, y) in cases {
+            if through[y][x].is_none() {
+                through[y][x] = Some(true);
+                found.push((x, y));
+            }
+        }
+
+        for y in 0..self.ways.len() {
+            for x in 0..self.ways[y].len() {
+                // Follow the way until a case already decided or the root
+                let mut chain = Vec::new();
+                let mut current = Some((x, y));
+                let answer = loop {
+                    let Some((cx, cy)) = current else {
+                        break false;
+                    };
+                    if let Some(answer) = through[cy][cx] {
+                        break answer;
+                    }
+                    chain.push((cx, cy));
+                    current = self.next_step(cx, cy);
+                };
+                for (cx, cy) in chain {
+                    through[cy][cx] = Some(answer);
+                    if answer {
+                        found.push((cx, cy));
+                    }
+                }
+            }
+        }
+        found
+    }
+}
+
+// Length of the shortest path from `start` to every case of the map, None when unreachable
+pub fn distances_from(start: (usize, usize), map: &[Vec<TypeCase>]) -> Vec<Vec<Option<usize>>> {
     let height = map.len();
+    let width = map.first().map_or(0, |row| row.len());
+    let mut distances = vec![vec![None; width]; height];
+    if start.0 >= width || start.1 >= height {
+        return distances;
+    }
+
+    // Breadth-first search, every move costs the same
+    let mut queue = VecDeque::new();
+    distances[start.1][start.0] = Some(0);
+    queue.push_back(start);
+    while let Some((x, y)) = queue.pop_front() {
+        let distance = distances[y][x].unwrap_or(0);
+        for (nx, ny) in NEIGHBOURS[..4]
+            .iter()
+            .filter_map(|&offset| neighbour(x, y, offset))
+        {
+            if nx >= width || ny >= height || distances[ny][nx].is_some() {
+                continue;
+            }
+            if map[ny][nx] == TypeCase::Wall || map[ny][nx] == TypeCase::Unknown {
+                continue;
+            }
+            distances[ny][nx] = Some(distance + 1);
+            queue.push_back((nx, ny));
+        }
+    }
+    distances
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PathfindingConfig;
+    use crate::mapfile::parse_ascii;
+
+    const MAP: &str = "\
+B..#....
+.#.#.##.
+.#...#o.
+.####.#.
+......#.
+";
+
+    // Room for diagonals, where only the exact heuristics keep the cheapest paths
+    const OPEN_MAP: &str = "\
+........
+.##..o..
+....#...
+.o..#..o
+........
+";
 
-    // Create the grid for seastar (true = wall, false = possible path)
-    let mut grid_data = vec![vec![false; width]; height];
-    for y in 0..height {
-        for x in 0..width {
-            grid_data[y][x] = map[y][x] == TypeCase::Wall || map[y][x] == TypeCase::Unknown;
+    fn configs() -> Vec<PathfindingConfig> {
+        let mut costly = PathfindingConfig::default();
+        costly.costs.ore = 5;
+        let diagonal = |corner_cutting| PathfindingConfig {
+            diagonal: true,
+            corner_cutting,
+            ..costly.clone()
+        };
+        vec![
+            PathfindingConfig::default(),
+            costly.clone(),
+            diagonal(CornerCutting::Never),
+            diagonal(CornerCutting::OneSide),
+            diagonal(CornerCutting::Always),
+        ]
+    }
+
+    fn pathfinders() -> Vec<Pathfinder> {
+        configs().iter().map(Pathfinder::from_config).collect()
+    }
+
+    fn diagonal(corner_cutting: CornerCutting) -> Pathfinder {
+        Pathfinder::from_config(&PathfindingConfig {
+            diagonal: true,
+            corner_cutting,
+            ..PathfindingConfig::default()
+        })
+    }
+
+    // Cost of a path, checking that each move is one the pathfinder allows
+    fn path_cost(path: &[(usize, usize)], map: &[Vec<TypeCase>], pathfinder: &Pathfinder) -> u64 {
+        let passable = |case: (usize, usize)| pathfinder.case_cost(map, case).is_some();
+        path.windows(2)
+            .map(|pair| {
+                let ((x, y), next) = (pair[0], pair[1]);
+                let (_, _, diagonal) = pathfinder
+                    .neighbours(x, y, &passable)
+                    .into_iter()
+                    .find(|&(nx, ny, _)| (nx, ny) == next)
+                    .unwrap_or_else(|| panic!("{:?} to {:?} is not a move", (x, y), next));
+                let step = if diagonal { DIAGONAL } else { STRAIGHT };
+                step * pathfinder.case_cost(map, next).unwrap()
+            })
+            .sum()
+    }
+
+    fn assert_same(field: &DistanceField, map: &[Vec<TypeCase>], pathfinder: &Pathfinder) {
+        let fresh = DistanceField::new(field.root, map, pathfinder);
+        for (y, row) in map.iter().enumerate() {
+            for x in 0..row.len() {
+                let case = (x, y);
+                assert_eq!(field.cost(x, y), fresh.cost(x, y), "cost of {:?}", case);
+                assert_eq!(field.moves(x, y), fresh.moves(x, y), "moves of {:?}", case);
+                assert_eq!(
+                    field.next_step(x, y),
+                    fresh.next_step(x, y),
+                    "next of {:?}",
+                    case
+                );
+                if let Some(path) = field.path(x, y) {
+                    assert_eq!(path.len(), field.moves(x, y).unwrap_or(0) + 1);
+                    assert_eq!(path.last(), Some(&field.root));
+                }
+            }
+        }
+    }
+
+    // Change the cases one after the other, the updated field must match a new one each time
+    fn check_changes(changes: &[(usize, usize, TypeCase)]) {
+        for pathfinder in pathfinders() {
+            let mut map = parse_ascii(MAP).unwrap();
+            let mut field = DistanceField::new((0, 0), &map, &pathfinder);
+            for (x, y, case) in changes {
+                let previous = std::mem::replace(&mut map[*y][*x], case.clone());
+                field.update(*x, *y, &previous, &map, &pathfinder);
+                assert_same(&field, &map, &pathfinder);
+            }
+        }
+    }
+
+    #[test]
+    fn opened_walls_update_the_field() {
+        check_changes(&[
+            (3, 0, TypeCase::Void),
+            (1, 1, TypeCase::Void),
+            (6, 3, TypeCase::Void),
+            (2, 3, TypeCase::Ore),
+        ]);
+    }
+
+    #[test]
+    fn added_walls_update_the_field() {
+        check_changes(&[
+            (2, 2, TypeCase::Wall),
+            (0, 2, TypeCase::Wall),
+            (7, 1, TypeCase::Wall),
+            (5, 4, TypeCase::Unknown),
+        ]);
+    }
+
+    #[test]
+    fn changed_costs_update_the_field() {
+        check_changes(&[
+            (6, 2, TypeCase::Void),
+            (2, 0, TypeCase::Ore),
+            (4, 2, TypeCase::Ore),
+            (2, 0, TypeCase::Void),
+        ]);
+    }
+
+    #[test]
+    fn moves_cost_10_straight_and_14_diagonal() {
+        let map = parse_ascii("....\n....\n....\n....\n").unwrap();
+        let straight = Pathfinder::default();
+        let path = straight.find_path((0, 0), (3, 0), &map).unwrap();
+        assert_eq!(path, vec![(0, 0), (1, 0), (2, 0), (3, 0)]);
+        assert_eq!(path_cost(&path, &map, &straight), 30);
+        let path = straight.find_path((0, 0), (3, 3), &map).unwrap();
+        assert_eq!(path_cost(&path, &map, &straight), 60);
+
+        let diagonal = diagonal(CornerCutting::Never);
+        let path = diagonal.find_path((0, 0), (3, 3), &map).unwrap();
+        assert_eq!(path, vec![(0, 0), (1, 1), (2, 2), (3, 3)]);
+        assert_eq!(path_cost(&path, &map, &diagonal), 42);
+        let path = diagonal.find_path((0, 0), (3, 1), &map).unwrap();
+        assert_eq!(path_cost(&path, &map, &diagonal), 34);
+    }
+
+    #[test]
+    fn terrain_costs_steer_the_path() {
+        let map = parse_ascii(".o.\n.#.\n...\n").unwrap();
+        let cheap = Pathfinder::default();
+        let path = cheap.find_path((0, 0), (2, 0), &map).unwrap();
+        assert_eq!(path, vec![(0, 0), (1, 0), (2, 0)]);
+
+        let mut config = PathfindingConfig::default();
+        config.costs.ore = 10;
+        let costly = Pathfinder::from_config(&config);
+        let path = costly.find_path((0, 0), (2, 0), &map).unwrap();
+        assert!(!path.contains(&(1, 0)));
+        assert_eq!(path_cost(&path, &map, &costly), 60);
+        // Reaching the ore itself pays its cost once
+        let path = costly.find_path((0, 0), (1, 0), &map).unwrap();
+        assert_eq!(path_cost(&path, &map, &costly), 100);
+    }
+
+    #[test]
+    fn corner_cutting_rules_decide_the_diagonals() {
+        let closed = parse_ascii(".#\n#.\n").unwrap();
+        assert_eq!(
+            diagonal(CornerCutting::Never).find_path((0, 0), (1, 1), &closed),
+            None
+        );
+        assert_eq!(
+            diagonal(CornerCutting::OneSide).find_path((0, 0), (1, 1), &closed),
+            None
+        );
+        assert_eq!(
+            diagonal(CornerCutting::Always).find_path((0, 0), (1, 1), &closed),
+            Some(vec![(0, 0), (1, 1)])
+        );
+
+        let half_open = parse_ascii(".#\n..\n").unwrap();
+        let around = diagonal(CornerCutting::Never).find_path((0, 0), (1, 1), &half_open);
+        assert_eq!(around, Some(vec![(0, 0), (0, 1), (1, 1)]));
+        for corner_cutting in [CornerCutting::OneSide, CornerCutting::Always] {
+            let path = diagonal(corner_cutting).find_path((0, 0), (1, 1), &half_open);
+            assert_eq!(path, Some(vec![(0, 0), (1, 1)]));
+        }
+    }
+
+    #[test]
+    fn blocked_goals_have_no_path() {
+        let map = parse_ascii(MAP).unwrap();
+        for pathfinder in pathfinders() {
+            // A wall, a case out of the map and a case walled off from the start
+            assert_eq!(pathfinder.find_path((0, 0), (3, 0), &map), None);
+            assert_eq!(pathfinder.find_path((0, 0), (8, 0), &map), None);
+            let enclosed = parse_ascii(".#.\n##.\n").unwrap();
+            assert_eq!(pathfinder.find_path((0, 0), (2, 0), &enclosed), None);
         }
+        // Unknown cases cannot be crossed either
+        let mut unknown = parse_ascii("...\n").unwrap();
+        unknown[0][1] = TypeCase::Unknown;
+        assert_eq!(
+            Pathfinder::default().find_path((0, 0), (2, 0), &unknown),
+            None
+        );
     }
 
-    let grid = Grid::from_2d(grid_data);
-    let start_point = Point::new(start.0 as isize, start.1 as isize);
-    let goal_point = Point::new(goal.0 as isize, goal.1 as isize);
+    #[test]
+    fn every_heuristic_finds_the_cheapest_path() {
+        for map in [MAP, OPEN_MAP] {
+            check_heuristics(&parse_ascii(map).unwrap());
+        }
+    }
 
-    // Use of the A* algorithm
-    astar(&grid, start_point, goal_point).map(|path| {
-        path.into_iter()
-            .map(|point| (point.x as usize, point.y as usize))
-            .collect()
-    })
+    // Every path found between two walkable cases costs as much as the way of a distance field
+    fn check_heuristics(map: &[Vec<TypeCase>]) {
+        let cases: Vec<(usize, usize)> = (0..map.len())
+            .flat_map(|y| (0..map[y].len()).map(move |x| (x, y)))
+            .filter(|&(x, y)| map[y][x] != TypeCase::Wall)
+            .collect();
+        for config in configs() {
+            let mut heuristics = vec![
+                HeuristicConfig::Octile,
+                HeuristicConfig::Euclidean,
+                HeuristicConfig::Dijkstra,
+            ];
+            if !config.diagonal {
+                heuristics.push(HeuristicConfig::Manhattan);
+            }
+            for heuristic in heuristics {
+                let pathfinder = Pathfinder::from_config(&PathfindingConfig {
+                    heuristic: Some(heuristic.clone()),
+                    ..config.clone()
+                });
+                for &goal in &cases {
+                    let field = DistanceField::new(goal, map, &pathfinder);
+                    for &start in &cases {
+                        let path = pathfinder.find_path(start, goal, map);
+                        let cost = path.map(|path| {
+                            assert_eq!(path.first(), Some(&start));
+                            assert_eq!(path.last(), Some(&goal));
+                            path_cost(&path, map, &pathfinder)
+                        });
+                        assert_eq!(
+                            cost,
+                            field.cost(start.0, start.1),
+                            "{:?} from {:?} to {:?}",
+                            heuristic,
+                            start,
+                            goal
+                        );
+                    }
+                }
+            }
+        }
+    }
 }
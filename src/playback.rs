@@ -0,0 +1,77 @@
+use crossterm::event::KeyCode;
+use std::time::{Duration, Instant};
+
+// Speed multipliers available with the [ and ] keys
+const SPEEDS: [f64; 7] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0, 16.0];
+const NORMAL_SPEED: usize = 2;
+
+// Converts the real time elapsed into a number of ticks to run, with pause and step
+pub struct Playback {
+    pub paused: bool,
+    speed_index: usize,
+    pending_steps: u64,
+    lag: Duration,
+    last_update: Instant,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Playback {
+    pub fn new() -> Self {
+        Playback {
+            paused: false,
+            speed_index: NORMAL_SPEED,
+            pending_steps: 0,
+            lag: Duration::ZERO,
+            last_update: Instant::now(),
+        }
+    }
+
+    pub fn speed(&self) -> f64 {
+        SPEEDS[self.speed_index]
+    }
+
+    // Apply a key press, returns false when the key is not a playback control
+    pub fn handle_key(&mut self, code: KeyCode) -> bool {
+        match code {
+            KeyCode::Char(' ') => self.paused = !self.paused,
+            KeyCode::Char('n') => {
+                self.paused = true;
+                self.pending_steps += 1;
+            }
+            KeyCode::Char('[') => self.speed_index = self.speed_index.saturating_sub(1),
+            KeyCode::Char(']') => self.speed_index = (self.speed_index + 1).min(SPEEDS.len() - 1),
+            _ => return false,
+        }
+        true
+    }
+
+    // Number of ticks to run since the last call
+    pub fn due_ticks(&mut self, tick_duration: Duration) -> u64 {
+        let elapsed = self.last_update.elapsed();
+        self.last_update = Instant::now();
+
+        if self.paused {
+            self.lag = Duration::ZERO;
+            return std::mem::take(&mut self.pending_steps);
+        }
+
+        self.lag += elapsed.mul_f64(self.speed());
+        let ticks = (self.lag.as_nanos() / tick_duration.as_nanos().max(1)) as u64;
+        self.lag -= tick_duration * ticks as u32;
+        ticks + std::mem::take(&mut self.pending_steps)
+    }
+
+    // Short description for the interface, like "x2" or "pause"
+    pub fn label(&self) -> String {
+        if self.paused {
+            "pause".to_string()
+        } else {
+            format!("x{}", self.speed())
+        }
+    }
+}
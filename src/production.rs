@@ -0,0 +1,104 @@
+use crate::base::Base;
+use crate::config::ProductionConfig;
+use crate::generation::TypeCase;
+
+// Decides which robot the base builds at each production cycle, `None` builds nothing
+pub trait ProductionStrategy {
+    fn choose(&self, base: &Base) -> Option<TypeCase>;
+}
+
+pub fn from_config(config: &ProductionConfig) -> Box<dyn ProductionStrategy> {
+    match config {
+        ProductionConfig::Ratio => Box::new(RatioStrategy),
+        ProductionConfig::FixedRatio {
+            collectors_per_explorer,
+        } => Box::new(FixedRatioStrategy {
+            collectors_per_explorer: *collectors_per_explorer,
+        }),
+        ProductionConfig::Demand {
+            resources_per_collector,
+        } => Box::new(DemandStrategy {
+            resources_per_collector: *resources_per_collector,
+        }),
+        ProductionConfig::Nothing => Box::new(NothingStrategy),
+    }
+}
+
+// Keep about two collectors per explorer, a collector is only built once one already exists
+pub struct RatioStrategy;
+
+impl ProductionStrategy for RatioStrategy {
+    fn choose(&self, base: &Base) -> Option<TypeCase> {
+        let (explorers, collectors) = base.robot_counts();
+        let ratio = if explorers == 0 {
+            0.0
+        } else {
+            collectors as f32 / explorers as f32
+        };
+
+        if ratio < 2.0 && collectors > 0 && base.can_afford(&TypeCase::Collector) {
+            Some(TypeCase::Collector)
+        } else if (ratio >= 2.0 || explorers == 0) && base.can_afford(&TypeCase::Explorer) {
+            Some(TypeCase::Explorer)
+        } else {
+            None
+        }
+    }
+}
+
+// Build whichever kind is below the configured ratio, saving up until it is affordable
+pub struct FixedRatioStrategy {
+    pub collectors_per_explorer: f64,
+}
+
+impl ProductionStrategy for FixedRatioStrategy {
+    fn choose(&self, base: &Base) -> Option<TypeCase> {
+        let (explorers, collectors) = base.robot_counts();
+        let wanted = if explorers > 0
+            && (collectors as f64) < explorers as f64 * self.collectors_per_explorer
+        {
+            TypeCase::Collector
+        } else {
+            TypeCase::Explorer
+        };
+        base.can_afford(&wanted).then_some(wanted)
+    }
+}
+
+// Build collectors while known resources wait to be collected, explorers while tiles are unknown
+pub struct DemandStrategy {
+    pub resources_per_collector: usize,
+}
+
+impl ProductionStrategy for DemandStrategy {
+    fn choose(&self, base: &Base) -> Option<TypeCase> {
+        let (_, collectors) = base.robot_counts();
+        let mut unknown = 0;
+        let mut resources = 0;
+        for case in base.known_map.iter().flatten() {
+            match case {
+                TypeCase::Unknown => unknown += 1,
+                TypeCase::Energy | TypeCase::Ore | TypeCase::Science => resources += 1,
+                _ => {}
+            }
+        }
+
+        let wanted = if resources > collectors * self.resources_per_collector {
+            TypeCase::Collector
+        } else if unknown > 0 {
+            TypeCase::Explorer
+        } else {
+            return None;
+        };
+        base.can_afford(&wanted).then_some(wanted)
+    }
+}
+
+// Keep the initial robots only
+pub struct NothingStrategy;
+
+impl ProductionStrategy for NothingStrategy {
+    fn choose(&self, _base: &Base) -> Option<TypeCase> {
+        None
+    }
+}
@@ -0,0 +1,99 @@
+use crate::base::Base;
+use crate::config::RegenerationRule;
+use crate::events::Event;
+use crate::generation::TypeCase;
+use crate::pathfinding::{neighbour, NEIGHBOURS};
+use crate::simulation::SimRng;
+use rand::Rng;
+
+// Grow back the resources whose regeneration interval ends on this tick, in the real map only
+pub fn regenerate(base: &mut Base, tick: u64, rng: &mut SimRng) {
+    if tick == 0 {
+        return;
+    }
+    let config = base.get_config();
+    let due: Vec<(TypeCase, RegenerationRule)> = config
+        .map
+        .regeneration
+        .rules()
+        .into_iter()
+        .filter(|(_, rule)| tick.is_multiple_of(config.simulation.ticks_for(rule.interval_ms)))
+        .map(|(resource, rule)| (resource, rule.clone()))
+        .collect();
+
+    for (resource, rule) in due {
+        spawn(base, resource, &rule, rng);
+    }
+}
+
+// Place a cluster of deposits in void cases, near the existing deposits of the same type if any
+fn spawn(base: &mut Base, resource: TypeCase, rule: &RegenerationRule, rng: &mut SimRng) {
+    let existing: Vec<(usize, usize)> = cases_of(base, &resource);
+    let mut candidates: Vec<(usize, usize)> = cases_of(base, &TypeCase::Void);
+    if rule.radius > 0 && !existing.is_empty() {
+        candidates.retain(|&(x, y)| {
+            existing
+                .iter()
+                .any(|&(ex, ey)| x.abs_diff(ex) + y.abs_diff(ey) <= rule.radius)
+        });
+    }
+    if candidates.is_empty() {
+        return;
+    }
+
+    let mut placed = vec![candidates[rng.random_range(0..candidates.len())]];
+    place(base, placed[0], &resource, rng);
+
+    // Grow the cluster from a random case already placed
+    while placed.len() < rule.cluster.max(1) {
+        let neighbours: Vec<(usize, usize)> = placed
+            .iter()
+            .flat_map(|&(x, y)| {
+                NEIGHBOURS[..4]
+                    .iter()
+                    .filter_map(move |&offset| neighbour(x, y, offset))
+            })
+            .filter(|&(x, y)| {
+                base.real_map
+                    .get(y)
+                    .and_then(|row| row.get(x))
+                    .is_some_and(|case| *case == TypeCase::Void)
+            })
+            .collect();
+        if neighbours.is_empty() {
+            break;
+        }
+        let case = neighbours[rng.random_range(0..neighbours.len())];
+        place(base, case, &resource, rng);
+        placed.push(case);
+    }
+}
+
+fn place(base: &mut Base, (x, y): (usize, usize), resource: &TypeCase, rng: &mut SimRng) {
+    let map = &base.get_config().map;
+    let min = map.deposit_min.max(1);
+    let amount = rng.random_range(min..=map.deposit_max.max(min));
+
+    base.real_map[y][x] = resource.clone();
+    if let Some(deposit) = base.deposits.get_mut(y).and_then(|row| row.get_mut(x)) {
+        *deposit = amount;
+    }
+    base.log(Event::ResourceSpawned {
+        x,
+        y,
+        resource: resource.clone(),
+        amount,
+    });
+}
+
+fn cases_of(base: &Base, case: &TypeCase) -> Vec<(usize, usize)> {
+    let mut cases = Vec::new();
+    for (y, row) in base.real_map.iter().enumerate() {
+        for (x, c) in row.iter().enumerate() {
+            if c == case {
+                cases.push((x, y));
+            }
+        }
+    }
+    cases
+}
@@ -0,0 +1,343 @@
+use crate::camera::Camera;
+use crate::playback::Playback;
+use crate::ui::{self, run_ui, Panels, Scene, Tui};
+use crossterm::event::{self, KeyCode, KeyEventKind};
+use rust_ereea::{Event, Map, RobotState, RobotStatus, Simulation, TimedEvent, TypeCase};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::time::{Duration, Instant};
+
+const FRAME_DURATION: Duration = Duration::from_millis(100);
+// Simulated time skipped by PageUp / PageDown
+const SEEK_MS: u64 = 10_000;
+
+// First line of a replay file, the state of the simulation when the recording started
+#[derive(Clone, Serialize, Deserialize)]
+struct ReplayHeader {
+    start_tick: u64,
+    tick_ms: u64,
+    known_map: Map,
+    robots: Vec<RobotStatus>,
+    base_x: usize,
+    base_y: usize,
+    energy: usize,
+    ore: usize,
+    science: usize,
+}
+
+// Writes the events of a running simulation as JSON lines, after a header line
+pub struct Recorder {
+    writer: BufWriter<File>,
+}
+
+impl Recorder {
+    pub fn create(path: &str, simulation: &Simulation) -> Result<Self, Box<dyn Error>> {
+        let base = &simulation.base;
+        let (base_x, base_y) = base.position();
+        let header = ReplayHeader {
+            start_tick: simulation.tick,
+            tick_ms: simulation.tick_duration().as_millis() as u64,
+            known_map: base.known_map().clone(),
+            robots: base.robots().iter().map(|robot| robot.status()).collect(),
+            base_x,
+            base_y,
+            energy: base.energy(),
+            ore: base.ore(),
+            science: base.science(),
+        };
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut writer, &header)?;
+        writer.write_all(b"\n")?;
+        Ok(Recorder { writer })
+    }
+
+    pub fn record(&mut self, events: &[TimedEvent]) -> Result<(), Box<dyn Error>> {
+        for event in events {
+            serde_json::to_writer(&mut self.writer, event)?;
+            self.writer.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+// A recording being played back, rebuilt from the header by applying the events in order
+pub struct Replay {
+    header: ReplayHeader,
+    events: Vec<TimedEvent>,
+    pub tick: u64,
+    pub last_tick: u64,
+    next_event: usize,
+    known_map: Map,
+    robots: Vec<RobotStatus>,
+    energy: usize,
+    ore: usize,
+    science: usize,
+}
+
+impl Replay {
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let mut lines = BufReader::new(File::open(path)?).lines();
+        let mut header: ReplayHeader = match lines.next() {
+            Some(line) => serde_json::from_str(&line?)?,
+            None => return Err("Fichier de relecture vide".into()),
+        };
+        // The charge of the batteries is not recorded, only shown for a running simulation
+        for robot in header.robots.iter_mut() {
+            robot.battery = None;
+        }
+
+        let mut events: Vec<TimedEvent> = Vec::new();
+        for line in lines {
+            events.push(serde_json::from_str(&line?)?);
+        }
+        let last_tick = events
+            .last()
+            .map_or(header.start_tick, |event| event.tick + 1);
+
+        Ok(Replay {
+            tick: header.start_tick,
+            last_tick,
+            next_event: 0,
+            known_map: header.known_map.clone(),
+            robots: header.robots.clone(),
+            energy: header.energy,
+            ore: header.ore,
+            science: header.science,
+            header,
+            events,
+        })
+    }
+
+    pub fn tick_duration(&self) -> Duration {
+        Duration::from_millis(self.header.tick_ms.max(1))
+    }
+
+    // Apply every event of the current tick
+    pub fn step(&mut self) {
+        if self.tick >= self.last_tick {
+            return;
+        }
+        while let Some(timed) = self.events.get(self.next_event) {
+            if timed.tick > self.tick {
+                break;
+            }
+            let event = timed.event.clone();
+            self.apply(event);
+            self.next_event += 1;
+        }
+        self.tick += 1;
+    }
+
+    // Going back in time restarts from the header
+    pub fn seek(&mut self, tick: u64) {
+        let tick = tick.clamp(self.header.start_tick, self.last_tick);
+        if tick < self.tick {
+            self.tick = self.header.start_tick;
+            self.next_event = 0;
+            self.known_map = self.header.known_map.clone();
+            self.robots = self.header.robots.clone();
+            self.energy = self.header.energy;
+            self.ore = self.header.ore;
+            self.science = self.header.science;
+        }
+        while self.tick < tick {
+            self.step();
+        }
+    }
+
+    fn apply(&mut self, event: Event) {
+        match event {
+            Event::RobotSpawned { robot, kind, x, y } => self.robots.push(RobotStatus {
+                id: robot,
+                state: if kind == TypeCase::Explorer {
+                    RobotState::Exploring
+                } else {
+                    RobotState::Idle
+                },
+                kind,
+                x,
+                y,
+                target: None,
+                carried: None,
+                path_length: 0,
+                battery: None,
+            }),
+            Event::RobotMoved { robot, x, y } => {
+                if let Some(status) = self.robot_mut(robot) {
+                    status.x = x;
+                    status.y = y;
+                    status.path_length = status.path_length.saturating_sub(1);
+                    if status.path_length == 0 && status.state == RobotState::Travelling {
+                        status.state = RobotState::Collecting;
+                    }
+                }
+            }
+            Event::PathComputed {
+                robot, to, length, ..
+            } => {
+                let home = (self.header.base_x, self.header.base_y);
+                if let Some(status) = self.robot_mut(robot) {
+                    status.path_length = length;
+                    // A collector going home empty handed has no destination any more
+                    if status.carried.is_none() {
+                        status.target = (to != home).then_some(to);
+                        status.state = RobotState::Travelling;
+                    }
+                }
+            }
+            // The path is planned again, the next path computed replaces it
+            Event::PathBlocked { robot, .. } => {
+                if let Some(status) = self.robot_mut(robot) {
+                    status.path_length = 0;
+                }
+            }
+            Event::DestinationAbandoned { robot, .. } => {
+                if let Some(status) = self.robot_mut(robot) {
+                    status.target = None;
+                    status.path_length = 0;
+                    status.state = RobotState::Idle;
+                }
+            }
+            Event::ResourceCollected {
+                robot, resource, ..
+            } => {
+                if let Some(status) = self.robot_mut(robot) {
+                    status.carried = Some(resource);
+                    status.state = RobotState::Returning;
+                }
+            }
+            Event::TileRevealed { x, y, case } => self.known_map[y][x] = case,
+            Event::ResourceDelivered {
+                robot,
+                resource,
+                amount,
+            } => {
+                match resource {
+                    TypeCase::Energy => self.energy += amount,
+                    TypeCase::Ore => self.ore += amount,
+                    TypeCase::Science => self.science += amount,
+                    _ => {}
+                }
+                if let Some(status) = self.robot_mut(robot) {
+                    status.carried = None;
+                    status.target = None;
+                    status.state = RobotState::Idle;
+                }
+            }
+            Event::RobotRecharged { energy, .. } => self.energy -= energy,
+            Event::RobotStranded { robot, .. } => {
+                if let Some(status) = self.robot_mut(robot) {
+                    status.state = RobotState::Stranded;
+                }
+            }
+            Event::RobotRescued { robot, .. } => {
+                if let Some(status) = self.robot_mut(robot) {
+                    status.state = if status.kind == TypeCase::Explorer {
+                        RobotState::Exploring
+                    } else if status.carried.is_some() {
+                        RobotState::Returning
+                    } else {
+                        RobotState::Idle
+                    };
+                }
+            }
+            Event::ResourcesSpent {
+                energy,
+                ore,
+                science,
+            } => {
+                self.energy -= energy;
+                self.ore -= ore;
+                self.science -= science;
+            }
+            _ => {}
+        }
+    }
+
+    fn robot_mut(&mut self, id: usize) -> Option<&mut RobotStatus> {
+        self.robots.iter_mut().find(|status| status.id == id)
+    }
+
+    pub fn scene(&self) -> Scene<'_> {
+        Scene {
+            known_map: &self.known_map,
+            robots: self.robots.clone(),
+            base_x: self.header.base_x,
+            base_y: self.header.base_y,
+        }
+    }
+}
+
+// Play a replay file in the terminal interface
+pub fn run(path: &str) -> Result<(), Box<dyn Error>> {
+    let mut replay = Replay::load(path)?;
+    let mut terminal = ui::init_terminal()?;
+    let result = run_loop(&mut terminal, &mut replay);
+    ui::restore_terminal(&mut terminal)?;
+    result
+}
+
+fn run_loop(terminal: &mut Tui, replay: &mut Replay) -> Result<(), Box<dyn Error>> {
+    let tick_duration = replay.tick_duration();
+    let seek_ticks = SEEK_MS / replay.header.tick_ms.max(1);
+    let mut camera = Camera::new(replay.header.base_x, replay.header.base_y);
+    let mut playback = Playback::new();
+    let mut panels = Panels::new();
+
+    loop {
+        for _ in 0..playback.due_ticks(tick_duration) {
+            replay.step();
+        }
+
+        let scene = replay.scene();
+        camera.update(&scene);
+        let robot_count = scene.robots.len();
+        let resources = format!(
+            "Relecture : tick {}/{} {} | Ressources : {} énergie, {} minerai, {} science | Robots : {}",
+            replay.tick,
+            replay.last_tick,
+            playback.label(),
+            replay.energy,
+            replay.ore,
+            replay.science,
+            robot_count
+        );
+        run_ui(terminal, &scene, &resources, &camera, &panels, None)?;
+
+        // Wait for the next frame while staying responsive to the keyboard
+        let deadline = Instant::now() + FRAME_DURATION;
+        while let Some(timeout) = deadline.checked_duration_since(Instant::now()) {
+            if !event::poll(timeout)? {
+                break;
+            }
+            let event::Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            if ui::is_quit_key(&key) {
+                return Ok(());
+            }
+            match key.code {
+                KeyCode::PageDown => replay.seek(replay.tick + seek_ticks),
+                KeyCode::PageUp => replay.seek(replay.tick.saturating_sub(seek_ticks)),
+                KeyCode::Home => replay.seek(0),
+                KeyCode::End => replay.seek(replay.last_tick),
+                code => {
+                    if !playback.handle_key(code) && !panels.handle_key(code) {
+                        camera.handle_key(code, &replay.scene());
+                    }
+                }
+            }
+        }
+    }
+}
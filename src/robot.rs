@@ -1,302 +1,738 @@
 use crate::base::Base;
+use crate::battery::Battery;
+use crate::config::{Config, ExplorationMode};
+use crate::events::Event;
 use crate::generation::TypeCase;
-use crate::pathfinding::find_path;
+use crate::simulation::{Cadence, SimRng};
 use rand::Rng;
-use std::sync::{Arc, Mutex};
-use std::thread::{self};
-use std::time::Duration;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 pub trait Robot: Send {
+    fn get_id(&self) -> usize;
     fn get_type(&self) -> TypeCase;
     fn get_position_x(&self) -> usize;
     fn get_position_y(&self) -> usize;
+    // Called by the simulation on every tick, the robot decides itself if it acts
+    fn update(&mut self, base: &mut Base, rng: &mut SimRng);
+    // Copy of the whole robot state, used to save the simulation
+    fn save(&self) -> SavedRobot;
+
+    // Introspection, what the robot is doing and where it is going
+    fn get_state(&self) -> RobotState;
+    fn get_target(&self) -> Option<(usize, usize)> {
+        None
+    }
+    fn get_carried_resource(&self) -> Option<TypeCase> {
+        None
+    }
+    fn get_path_length(&self) -> usize {
+        0
+    }
+    // Charge left in percent, None for robots without a battery
+    fn get_battery(&self) -> Option<usize> {
+        None
+    }
+
+    fn status(&self) -> RobotStatus {
+        RobotStatus {
+            id: self.get_id(),
+            kind: self.get_type(),
+            x: self.get_position_x(),
+            y: self.get_position_y(),
+            state: self.get_state(),
+            target: self.get_target(),
+            carried: self.get_carried_resource(),
+            path_length: self.get_path_length(),
+            battery: self.get_battery(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum RobotState {
+    #[default]
+    Idle,
+    Exploring,
+    Travelling,
+    Collecting,
+    Returning,
+    // Waiting at the base for energy to refill the battery
+    Recharging,
+    // Out of battery away from the base, waiting for a collector
+    Stranded,
 }
 
+// Plain description of a robot, enough for the interface to draw and inspect it
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RobotStatus {
+    pub id: usize,
+    pub kind: TypeCase,
+    pub x: usize,
+    pub y: usize,
+    #[serde(default)]
+    pub state: RobotState,
+    #[serde(default)]
+    pub target: Option<(usize, usize)>,
+    #[serde(default)]
+    pub carried: Option<TypeCase>,
+    #[serde(default)]
+    pub path_length: usize,
+    #[serde(default)]
+    pub battery: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum SavedRobot {
+    Explorer(Explorer),
+    Collector(Collector),
+}
+
+impl SavedRobot {
+    pub fn into_robot(self) -> Box<dyn Robot + Send> {
+        match self {
+            SavedRobot::Explorer(explorer) => Box::new(explorer),
+            SavedRobot::Collector(collector) => Box::new(collector),
+        }
+    }
+}
+
+// Serde adapter for the list of deployed robots, `#[serde(with = "crate::robot::saved_robots")]`
+pub mod saved_robots {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        robots: &[Box<dyn Robot + Send>],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(robots.iter().map(|robot| robot.save()))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Box<dyn Robot + Send>>, D::Error> {
+        let robots = Vec::<SavedRobot>::deserialize(deserializer)?;
+        Ok(robots.into_iter().map(SavedRobot::into_robot).collect())
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Explorer {
-    position_x: Arc<Mutex<usize>>,
-    position_y: Arc<Mutex<usize>>,
+    id: usize,
+    position_x: usize,
+    position_y: usize,
+    vision_range: usize,
+    cadence: Cadence,
+    #[serde(default)]
+    battery: Option<Battery>,
+    #[serde(default)]
+    stranded: bool,
+    #[serde(default)]
+    exploration: ExplorationMode,
+    // Case the explorer is heading to and the path to it, reversed so that `pop` gives the next
+    // step, only used by the frontier mode
+    #[serde(default)]
+    target: Option<(usize, usize)>,
+    #[serde(default)]
+    path: Vec<(usize, usize)>,
 }
 
 impl Explorer {
-    pub fn new(
-        map_width: usize,
-        map_height: usize,
-        x: usize,
-        y: usize,
-        base_ref: Arc<Mutex<Base>>,
-    ) -> Self {
-        let explorateur = Explorer {
-            position_x: Arc::new(Mutex::new(x)),
-            position_y: Arc::new(Mutex::new(y)),
-        };
-
-        let position_x = Arc::clone(&explorateur.position_x);
-        let position_y = Arc::clone(&explorateur.position_y);
-        let base = Arc::clone(&base_ref);
-
-        thread::spawn(move || {
-            // Récupérer la configuration du robot
-            let vision_range;
-            let move_delay_ms;
-            if let Ok(base_guard) = base.lock() {
-                let config = base_guard.get_explorer_config();
-                vision_range = config.vision_range;
-                move_delay_ms = config.move_delay_ms;
-            } else {
-                vision_range = 2; // Valeur par défaut
-                move_delay_ms = 10; // Valeur par défaut
-            }
+    pub fn new(id: usize, x: usize, y: usize, config: &Config) -> Self {
+        let explorer_config = &config.robots.explorer;
+        Explorer {
+            id,
+            position_x: x,
+            position_y: y,
+            vision_range: explorer_config.vision_range,
+            cadence: Cadence::new(config.simulation.ticks_for(explorer_config.move_delay_ms)),
+            battery: explorer_config.battery.as_ref().map(Battery::new),
+            stranded: false,
+            exploration: explorer_config.exploration,
+            target: None,
+            path: Vec::new(),
+        }
+    }
 
-            loop {
-                let x = *position_x.lock().unwrap();
-                let y = *position_y.lock().unwrap();
-                let mut rng = rand::rng();
-
-                let mut directions = vec![];
-                let possible_moves = [
-                    (0, -1, 0), // Up
-                    (0, 1, 1),  // Down
-                    (-1, 0, 2), // Left
-                    (1, 0, 3),  // Right
-                ];
-
-                if let Ok(base) = base.lock() {
-                    if let Ok(known_map) = base.known_map.lock() {
-                        if let Ok(real_map) = base.real_map.lock() {
-                            for (dx, dy, dir) in possible_moves.iter() {
-                                let new_x = x as i32 + dx;
-                                let new_y = y as i32 + dy;
-
-                                if new_x >= 0
-                                    && new_x < map_width as i32
-                                    && new_y >= 0
-                                    && new_y < map_height as i32
-                                {
-                                    let new_x = new_x as usize;
-                                    let new_y = new_y as usize;
-
-                                    if let Some(case_type) =
-                                        real_map.get(new_y).and_then(|row| row.get(new_x))
-                                    {
-                                        if *case_type != TypeCase::Wall {
-                                            let weight =
-                                                if let Some(known_row) = known_map.get(new_y) {
-                                                    if let Some(known_type) = known_row.get(new_x) {
-                                                        if *known_type == TypeCase::Unknown {
-                                                            3 // Plus de poids pour les cases inconnues
-                                                        } else {
-                                                            1
-                                                        }
-                                                    } else {
-                                                        3
-                                                    }
-                                                } else {
-                                                    3
-                                                };
-
-                                            for _ in 0..weight {
-                                                directions.push(*dir);
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
+    // Take one step towards the current frontier, choosing a new one when it is reached or
+    // revealed, returns false when the random walk should take over
+    fn frontier_step(&mut self, base: &mut Base, at_base: bool, moves_left: usize) -> bool {
+        let position = (self.position_x, self.position_y);
+        let home = (base.position_x, base.position_y);
+        let stale = match self.target {
+            Some(target) if target == home => self.path.is_empty(),
+            Some(target) => self.path.is_empty() || !base.is_frontier(target.0, target.1),
+            None => true,
+        } || self.path.iter().any(|&(x, y)| !base.is_walkable(x, y));
+
+        if stale {
+            self.path.clear();
+            self.target = None;
+            let path = match base.next_frontier(self.id, position.0, position.1, moves_left) {
+                Some(path) => Some(path),
+                // No frontier within reach of the battery, wait for one at the base where the
+                // battery is recharged instead of wandering
+                None if self.battery.is_some() => {
+                    if at_base {
+                        return true;
                     }
+                    base.find_path(home, position)
                 }
+                None => None,
+            };
+            let Some(mut path) = path else {
+                base.release_frontier(self.id);
+                return false;
+            };
+            if path.last() == Some(&position) {
+                path.pop();
+            }
+            self.target = path.first().copied();
+            self.path = path;
+        }
 
-                if !directions.is_empty() {
-                    let direction = directions[rng.random_range(0..directions.len())];
-                    let (dx, dy) = match direction {
-                        0 => (0, -1), // Up
-                        1 => (0, 1),  // Down
-                        2 => (-1, 0), // Left
-                        _ => (1, 0),  // Right
-                    };
+        let Some((next_x, next_y)) = self.path.pop() else {
+            return false;
+        };
+        self.position_x = next_x;
+        self.position_y = next_y;
+        if let Some(battery) = &mut self.battery {
+            battery.drain();
+        }
+        base.log(Event::RobotMoved {
+            robot: self.id,
+            x: next_x,
+            y: next_y,
+        });
+        true
+    }
 
-                    let new_x = (x as i32 + dx) as usize;
-                    let new_y = (y as i32 + dy) as usize;
+    // Move to a random neighbour, an unknown one being three times more likely
+    fn random_step(&mut self, base: &mut Base, rng: &mut SimRng, at_base: bool, moves_left: usize) {
+        let x = self.position_x;
+        let y = self.position_y;
+        // Only the moves that keep the base within reach of the battery are allowed
+        let current_distance = match self.battery {
+            Some(_) => base.home_distance(x, y),
+            None => None,
+        };
 
-                    *position_x.lock().unwrap() = new_x;
-                    *position_y.lock().unwrap() = new_y;
-                }
+        let map_width = base.width();
+        let map_height = base.height();
+
+        let mut directions = vec![];
+        let possible_moves = [
+            (0, -1, 0), // Up
+            (0, 1, 1),  // Down
+            (-1, 0, 2), // Left
+            (1, 0, 3),  // Right
+        ];
+
+        for (dx, dy, dir) in possible_moves.iter() {
+            let new_x = x as i32 + dx;
+            let new_y = y as i32 + dy;
+
+            if new_x >= 0 && new_x < map_width as i32 && new_y >= 0 && new_y < map_height as i32 {
+                let new_x = new_x as usize;
+                let new_y = new_y as usize;
+
+                if base.real_map[new_y][new_x] != TypeCase::Wall {
+                    // Unknown cases are at most one move further than the current one
+                    let distance = match (base.home_distance(new_x, new_y), current_distance) {
+                        (Some(distance), _) => Some(distance),
+                        (None, Some(current)) => Some(current + 1),
+                        (None, None) => None,
+                    };
+                    if distance.is_some_and(|distance| distance > moves_left - 1) {
+                        continue;
+                    }
+
+                    let weight = if base.known_map[new_y][new_x] == TypeCase::Unknown {
+                        3 // Plus de poids pour les cases inconnues
+                    } else {
+                        1
+                    };
 
-                if let Ok(base) = base.lock() {
-                    for dy in -(vision_range as i32)..=(vision_range as i32) {
-                        for dx in -(vision_range as i32)..=(vision_range as i32) {
-                            if (dx.abs() + dy.abs()) as usize <= vision_range {
-                                let new_x = x as i32 + dx;
-                                let new_y = y as i32 + dy;
-
-                                if new_x >= 0
-                                    && new_y >= 0
-                                    && new_x < map_width as i32
-                                    && new_y < map_height as i32
-                                {
-                                    let new_x = new_x as usize;
-                                    let new_y = new_y as usize;
-
-                                    if let Ok(real_map) = base.real_map.lock() {
-                                        if let Some(case_type) =
-                                            real_map.get(new_y).and_then(|row| row.get(new_x))
-                                        {
-                                            let case_type = case_type.clone();
-                                            base.update_map(new_x, new_y, case_type);
-                                        }
-                                    }
-                                }
-                            }
-                        }
+                    for _ in 0..weight {
+                        directions.push(*dir);
                     }
                 }
-
-                thread::sleep(Duration::from_millis(move_delay_ms));
             }
-        });
+        }
+
+        let step = if !directions.is_empty() {
+            let direction = directions[rng.random_range(0..directions.len())];
+            let (dx, dy) = match direction {
+                0 => (0, -1), // Up
+                1 => (0, 1),  // Down
+                2 => (-1, 0), // Left
+                _ => (1, 0),  // Right
+            };
+            Some(((x as i32 + dx) as usize, (y as i32 + dy) as usize))
+        } else if self.battery.is_some() && !at_base {
+            // The budget counts the moves of the way home, which may be diagonal, so that way
+            // is followed when no straight move keeps the base within reach
+            base.distance_field().next_step(x, y)
+        } else {
+            None
+        };
 
-        explorateur
+        if let Some((next_x, next_y)) = step {
+            self.position_x = next_x;
+            self.position_y = next_y;
+            if let Some(battery) = &mut self.battery {
+                battery.drain();
+            }
+            base.log(Event::RobotMoved {
+                robot: self.id,
+                x: next_x,
+                y: next_y,
+            });
+        } else if self.battery.is_some() && !at_base {
+            // No move keeps the base within reach, only a collector can bring it back
+            self.stranded = true;
+            base.report_stranded(self.id, x, y);
+        }
     }
 }
 
 impl Robot for Explorer {
+    fn get_id(&self) -> usize {
+        self.id
+    }
+
     fn get_type(&self) -> TypeCase {
         TypeCase::Explorer
     }
 
     fn get_position_x(&self) -> usize {
-        *self.position_x.lock().unwrap()
+        self.position_x
     }
 
     fn get_position_y(&self) -> usize {
-        *self.position_y.lock().unwrap()
+        self.position_y
+    }
+
+    fn save(&self) -> SavedRobot {
+        SavedRobot::Explorer(self.clone())
+    }
+
+    fn get_state(&self) -> RobotState {
+        if self.stranded {
+            RobotState::Stranded
+        } else if self
+            .battery
+            .as_ref()
+            .is_some_and(|battery| !battery.can_move())
+        {
+            RobotState::Recharging
+        } else {
+            RobotState::Exploring
+        }
+    }
+
+    fn get_target(&self) -> Option<(usize, usize)> {
+        self.target
+    }
+
+    fn get_path_length(&self) -> usize {
+        self.path.len()
+    }
+
+    fn get_battery(&self) -> Option<usize> {
+        self.battery.as_ref().map(Battery::percent)
+    }
+
+    fn update(&mut self, base: &mut Base, rng: &mut SimRng) {
+        if !self.cadence.ready() {
+            return;
+        }
+
+        let x = self.position_x;
+        let y = self.position_y;
+        let at_base = x == base.position_x && y == base.position_y;
+
+        // Refill the battery at the base, or when a collector reached the stranded explorer
+        if let Some(battery) = &mut self.battery {
+            if self.stranded {
+                if !base.take_rescue(self.id) {
+                    return;
+                }
+                self.stranded = false;
+                battery.recharge(self.id, base);
+            } else if at_base && !battery.is_full() {
+                battery.recharge(self.id, base);
+            }
+        }
+        let moves_left = self
+            .battery
+            .as_ref()
+            .map_or(usize::MAX, Battery::moves_left);
+        if moves_left == 0 {
+            if !at_base {
+                self.stranded = true;
+                self.target = None;
+                self.path.clear();
+                base.report_stranded(self.id, x, y);
+            }
+            return;
+        }
+        let moved = match self.exploration {
+            ExplorationMode::Frontier => self.frontier_step(base, at_base, moves_left),
+            ExplorationMode::RandomWalk => false,
+        };
+        if !moved {
+            self.random_step(base, rng, at_base, moves_left);
+        }
+
+        let map_width = base.width();
+        let map_height = base.height();
+
+        let vision_range = self.vision_range as i32;
+        for dy in -vision_range..=vision_range {
+            for dx in -vision_range..=vision_range {
+                if dx.abs() + dy.abs() <= vision_range {
+                    let new_x = x as i32 + dx;
+                    let new_y = y as i32 + dy;
+
+                    if new_x >= 0
+                        && new_y >= 0
+                        && new_x < map_width as i32
+                        && new_y < map_height as i32
+                    {
+                        let new_x = new_x as usize;
+                        let new_y = new_y as usize;
+                        let case_type = base.real_map[new_y][new_x].clone();
+                        base.update_map(new_x, new_y, case_type);
+                    }
+                }
+            }
+        }
     }
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Collector {
-    position_x: Arc<Mutex<usize>>,
-    position_y: Arc<Mutex<usize>>,
-    at_base: Arc<Mutex<bool>>,
-    path: Arc<Mutex<Vec<(usize, usize)>>>,
-    collected_resource: Arc<Mutex<Option<TypeCase>>>,
-    destination: Arc<Mutex<Option<(usize, usize)>>>,
+    id: usize,
+    position_x: usize,
+    position_y: usize,
+    at_base: bool,
+    path: Vec<(usize, usize)>,
+    collected_resource: Option<TypeCase>,
+    // Units of the carried resource
+    collected_amount: usize,
+    destination: Option<(usize, usize)>,
+    cadence: Cadence,
+    #[serde(default)]
+    battery: Option<Battery>,
+    #[serde(default)]
+    stranded: bool,
+    // Stranded robot the collector is going to, instead of a resource
+    #[serde(default)]
+    rescue: Option<usize>,
 }
 
 impl Collector {
-    pub fn new(x: usize, y: usize, base_ref: Arc<Mutex<Base>>) -> Self {
-        let collecteur = Collector {
-            position_x: Arc::new(Mutex::new(x)),
-            position_y: Arc::new(Mutex::new(y)),
-            at_base: Arc::new(Mutex::new(true)),
-            path: Arc::new(Mutex::new(Vec::new())),
-            collected_resource: Arc::new(Mutex::new(None)),
-            destination: Arc::new(Mutex::new(None)),
+    pub fn new(id: usize, x: usize, y: usize, config: &Config) -> Self {
+        Collector {
+            id,
+            position_x: x,
+            position_y: y,
+            at_base: true,
+            path: Vec::new(),
+            collected_resource: None,
+            collected_amount: 0,
+            destination: None,
+            cadence: Cadence::new(
+                config
+                    .simulation
+                    .ticks_for(config.robots.collector.move_delay_ms),
+            ),
+            battery: config.robots.collector.battery.as_ref().map(Battery::new),
+            stranded: false,
+            rescue: None,
+        }
+    }
+
+    // Whether the battery lasts for the way to `target` and back, from the base
+    fn can_reach(&self, base: &mut Base, target: (usize, usize)) -> bool {
+        let Some(battery) = &self.battery else {
+            return true;
         };
+        base.round_trip(target.0, target.1)
+            .is_some_and(|moves| moves <= battery.moves_left())
+    }
 
-        let position_x = Arc::clone(&collecteur.position_x);
-        let position_y = Arc::clone(&collecteur.position_y);
-        let path = Arc::clone(&collecteur.path);
-        let at_base = Arc::clone(&collecteur.at_base);
-        let collected_resource = Arc::clone(&collecteur.collected_resource);
-        let destination = Arc::clone(&collecteur.destination);
-        let base = Arc::clone(&base_ref);
-
-        thread::spawn(move || {
-            // Récupérer la configuration du robot
-            let move_delay_ms = if let Ok(base_guard) = base.lock() {
-                base_guard.get_collector_config().move_delay_ms
-            } else {
-                100 // Valeur par défaut
-            };
+    // Leave the base for the oldest stranded robot, returns false when there is none in reach
+    fn start_rescue(&mut self, base: &mut Base) -> bool {
+        let Some((robot, target)) = base.claim_stranded() else {
+            return false;
+        };
+        if self.can_reach(base, target) {
+            if let Some(new_path) = self.plan_path(base, target) {
+                self.path = new_path;
+                self.destination = Some(target);
+                self.rescue = Some(robot);
+                return true;
+            }
+        }
+        base.unclaim_stranded(robot, target.0, target.1);
+        false
+    }
 
-            loop {
-                if let Ok(base_guard) = base.lock() {
-                    let curr_x = *position_x.lock().unwrap();
-                    let curr_y = *position_y.lock().unwrap();
-                    let mut path_guard = path.lock().unwrap();
-                    let is_at_base =
-                        curr_x == base_guard.position_x && curr_y == base_guard.position_y;
-                    *at_base.lock().unwrap() = is_at_base;
-                    let has_resource = collected_resource.lock().unwrap().is_some();
-
-                    // If the robot is at the base and has no resource, look for a new destination
-                    if is_at_base && !has_resource && path_guard.is_empty() {
-                        if let Some((target_x, target_y)) = base_guard.next_resource() {
-                            if let Ok(known_map) = base_guard.known_map.lock() {
-                                if let Some(new_path) =
-                                    find_path((target_x, target_y), (curr_x, curr_y), &known_map)
-                                {
-                                    *path_guard = new_path;
-                                    *destination.lock().unwrap() = Some((target_x, target_y));
-                                }
-                            }
-                        }
-                    }
-                    // If the robot has a resource and is at the base, drop it
-                    else if is_at_base && has_resource {
-                        if let Some(resource) = collected_resource.lock().unwrap().clone() {
-                            base_guard.add_resource(resource);
-                        }
-                        *collected_resource.lock().unwrap() = None;
-                    }
-                    // If the robot has a destination and is not on a path
-                    else if path_guard.is_empty() {
-                        if let Some((target_x, target_y)) = *destination.lock().unwrap() {
-                            // if the robot is on the target, collect the resource
-                            if curr_x == target_x && curr_y == target_y {
-                                // collect the resource
-                                if !has_resource {
-                                    if let Ok(mut map) = base_guard.real_map.lock() {
-                                        let resource = map[curr_y][curr_x].clone();
-                                        *collected_resource.lock().unwrap() =
-                                            Some(resource.clone());
-
-                                        //Update the map
-                                        map[curr_y][curr_x] = TypeCase::Void;
-                                        if let Ok(mut known_map) = base_guard.known_map.lock() {
-                                            known_map[curr_y][curr_x] = TypeCase::Void;
-                                        }
-
-                                        base_guard.release_resource(curr_x, curr_y);
-
-                                        // Look for a new destination
-                                        if let Ok(known_map) = base_guard.known_map.lock() {
-                                            if let Some(new_path) = find_path(
-                                                (base_guard.position_x, base_guard.position_y),
-                                                (curr_x, curr_y),
-                                                &known_map,
-                                            ) {
-                                                *path_guard = new_path;
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    // If the robot is on a path, follow the path
-                    else if let Some((next_x, next_y)) = path_guard.pop() {
-                        *position_x.lock().unwrap() = next_x;
-                        *position_y.lock().unwrap() = next_y;
-                    }
-                }
-                thread::sleep(Duration::from_millis(move_delay_ms));
+    // Leave the base for the next resource the battery can make the trip to
+    fn start_collection(&mut self, base: &mut Base) {
+        let moves_left = self
+            .battery
+            .as_ref()
+            .map_or(usize::MAX, Battery::moves_left);
+        let Some(target) = base.next_resource(moves_left) else {
+            return;
+        };
+        match self.plan_path(base, target) {
+            Some(new_path) => {
+                self.path = new_path;
+                self.destination = Some(target);
+            }
+            None => {
+                base.release_resource(target.0, target.1);
+                base.mark_unreachable(target.0, target.1);
+            }
+        }
+    }
+
+    // Make sure the destination is still worth reaching and the path can still be walked on,
+    // planning again when the known map changed under it. Returns false when there is no path
+    // left to follow
+    fn check_path(&mut self, base: &mut Base) -> bool {
+        // The resource ran out while on the way
+        if let Some(target) = self.destination {
+            if self.rescue.is_none()
+                && self.collected_resource.is_none()
+                && !base.has_resource(target.0, target.1)
+            {
+                self.abandon(base);
+                return !self.path.is_empty();
             }
+        }
+
+        let Some(&(x, y)) = self.path.iter().find(|&&(x, y)| !base.is_walkable(x, y)) else {
+            return true;
+        };
+        base.log(Event::PathBlocked {
+            robot: self.id,
+            x,
+            y,
         });
+        // The first case of the reversed path is where the robot was going
+        let goal = self.path[0];
+        self.path.clear();
+        match self.plan_path(base, goal) {
+            Some(new_path) => self.path = new_path,
+            None if self.destination.is_some() && self.collected_resource.is_none() => {
+                self.abandon(base)
+            }
+            None => {}
+        }
+        !self.path.is_empty()
+    }
+
+    // Give the destination back to the base and head home
+    fn abandon(&mut self, base: &mut Base) {
+        let Some(target) = self.destination.take() else {
+            return;
+        };
+        match self.rescue.take() {
+            Some(robot) => base.unclaim_stranded(robot, target.0, target.1),
+            None => base.release_resource(target.0, target.1),
+        }
+        base.log(Event::DestinationAbandoned {
+            robot: self.id,
+            x: target.0,
+            y: target.1,
+        });
+        self.path.clear();
+        self.go_home(base);
+    }
+
+    // Head back to the base, tried again at the next move when no known path leads there
+    fn go_home(&mut self, base: &mut Base) {
+        if let Some(new_path) = self.plan_path(base, (base.position_x, base.position_y)) {
+            self.path = new_path;
+        }
+    }
 
-        collecteur
+    // Path from the current position to `target`, reversed so that `pop` gives the next step
+    fn plan_path(&self, base: &mut Base, target: (usize, usize)) -> Option<Vec<(usize, usize)>> {
+        let from = (self.position_x, self.position_y);
+        let path = base.find_path(target, from);
+        match &path {
+            Some(path) => base.log(Event::PathComputed {
+                robot: self.id,
+                from,
+                to: target,
+                length: path.len(),
+            }),
+            None => base.log(Event::PathFailed {
+                robot: self.id,
+                from,
+                to: target,
+            }),
+        }
+        path
     }
 }
 
 impl Robot for Collector {
+    fn get_id(&self) -> usize {
+        self.id
+    }
+
     fn get_type(&self) -> TypeCase {
         TypeCase::Collector
     }
 
     fn get_position_x(&self) -> usize {
-        *self.position_x.lock().unwrap()
+        self.position_x
     }
 
     fn get_position_y(&self) -> usize {
-        *self.position_y.lock().unwrap()
+        self.position_y
+    }
+
+    fn save(&self) -> SavedRobot {
+        SavedRobot::Collector(self.clone())
+    }
+
+    fn get_state(&self) -> RobotState {
+        if self.stranded {
+            RobotState::Stranded
+        } else if self.at_base
+            && self
+                .battery
+                .as_ref()
+                .is_some_and(|battery| !battery.is_full())
+            && self.path.is_empty()
+        {
+            RobotState::Recharging
+        } else if self.collected_resource.is_some() {
+            RobotState::Returning
+        } else if !self.path.is_empty() {
+            RobotState::Travelling
+        } else if self.destination.is_some() && !self.at_base {
+            RobotState::Collecting
+        } else {
+            RobotState::Idle
+        }
+    }
+
+    fn get_target(&self) -> Option<(usize, usize)> {
+        self.destination
+    }
+
+    fn get_carried_resource(&self) -> Option<TypeCase> {
+        self.collected_resource.clone()
+    }
+
+    fn get_path_length(&self) -> usize {
+        self.path.len()
+    }
+
+    fn get_battery(&self) -> Option<usize> {
+        self.battery.as_ref().map(Battery::percent)
+    }
+
+    fn update(&mut self, base: &mut Base, _rng: &mut SimRng) {
+        if !self.cadence.ready() {
+            return;
+        }
+
+        let curr_x = self.position_x;
+        let curr_y = self.position_y;
+        self.at_base = curr_x == base.position_x && curr_y == base.position_y;
+        let has_resource = self.collected_resource.is_some();
+
+        // A stranded collector waits for another one to reach it
+        if self.stranded {
+            if !base.take_rescue(self.id) {
+                return;
+            }
+            self.stranded = false;
+            if let Some(battery) = &mut self.battery {
+                battery.recharge(self.id, base);
+            }
+        }
+
+        // If the robot is at the base and has no resource, look for a new destination
+        if self.at_base && !has_resource && self.path.is_empty() {
+            // With a partial charge when the base has no energy left, only for the trips in reach
+            if let Some(battery) = &mut self.battery {
+                battery.recharge(self.id, base);
+            }
+            if !self.start_rescue(base) {
+                self.start_collection(base);
+            }
+        }
+        // If the robot has a resource and is at the base, drop it
+        else if self.at_base && has_resource {
+            if let Some(resource) = self.collected_resource.take() {
+                self.destination = None;
+                base.add_resource(resource.clone(), self.collected_amount);
+                base.log(Event::ResourceDelivered {
+                    robot: self.id,
+                    resource,
+                    amount: self.collected_amount,
+                });
+            }
+        }
+        // If the robot is away from the base and not on a path
+        else if self.path.is_empty() {
+            match self.destination {
+                // if the robot reached a stranded robot, hand it over to the base and go back
+                Some(target) if target == (curr_x, curr_y) && self.rescue.is_some() => {
+                    if let Some(robot) = self.rescue.take() {
+                        base.rescue(robot, self.id);
+                    }
+                    self.destination = None;
+                    self.go_home(base);
+                }
+                // if the robot is on the target, collect the resource unless it ran out meanwhile
+                Some(target) if target == (curr_x, curr_y) && !has_resource => {
+                    if base.has_resource(curr_x, curr_y) {
+                        let (resource, amount) = base.collect_resource(self.id, curr_x, curr_y);
+                        self.collected_resource = Some(resource);
+                        self.collected_amount = amount;
+                        self.go_home(base);
+                    } else {
+                        self.abandon(base);
+                    }
+                }
+                // No known path led back to the base, try again
+                _ => self.go_home(base),
+            }
+        }
+        // Out of battery on the way, wait for a rescue where the robot stands
+        else if self
+            .battery
+            .as_ref()
+            .is_some_and(|battery| !battery.can_move())
+        {
+            self.stranded = true;
+            base.report_stranded(self.id, curr_x, curr_y);
+        }
+        // If the robot is on a path, follow the path while it still leads somewhere
+        else if self.check_path(base) {
+            if let Some((next_x, next_y)) = self.path.pop() {
+                if let Some(battery) = &mut self.battery {
+                    battery.drain();
+                }
+                self.position_x = next_x;
+                self.position_y = next_y;
+                base.log(Event::RobotMoved {
+                    robot: self.id,
+                    x: next_x,
+                    y: next_y,
+                });
+            }
+        }
     }
 }
@@ -0,0 +1,49 @@
+use crate::simulation::Simulation;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+
+pub const DEFAULT_SAVE_PATH: &str = "save.json";
+
+// Write the full simulation state (maps, resources, robots, random generator) as JSON
+pub fn save(simulation: &Simulation, path: &str) -> Result<(), Box<dyn Error>> {
+    let writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer(writer, simulation)?;
+    Ok(())
+}
+
+pub fn load(path: &str) -> Result<Simulation, Box<dyn Error>> {
+    let reader = BufReader::new(File::open(path)?);
+    let simulation = serde_json::from_reader(reader)?;
+    Ok(simulation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::tests::{simulation, state};
+
+    fn run(simulation: &mut Simulation, ticks: usize) {
+        for _ in 0..ticks {
+            simulation.step();
+        }
+    }
+
+    #[test]
+    fn loaded_save_continues_like_the_original() {
+        let mut uninterrupted = simulation(7);
+        run(&mut uninterrupted, 3000);
+
+        let mut saved = simulation(7);
+        run(&mut saved, 1000);
+        let path = std::env::temp_dir().join(format!("ereea-save-{}.json", std::process::id()));
+        let path = path.to_str().expect("temporary path is valid UTF-8");
+        save(&saved, path).expect("the simulation saves");
+        let loaded = load(path);
+        std::fs::remove_file(path).ok();
+        let mut resumed = loaded.expect("the save loads");
+        run(&mut resumed, 2000);
+
+        assert_eq!(state(&resumed), state(&uninterrupted));
+    }
+}
@@ -0,0 +1,327 @@
+use crate::config::{Config, DEFAULT_CONFIG_PATH};
+use crate::events::Event;
+use crate::generation::TypeCase;
+use crate::robot::RobotState;
+use crate::simulation::Simulation;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Points given by a fully reached goal, and at most by the time left when the scenario is won
+const GOAL_POINTS: f64 = 1000.0;
+const TIME_POINTS: f64 = 1000.0;
+
+// The [scenario] table of a scenario file, the other tables override the configuration
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Scenario {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    // Configuration the scenario starts from, relative to the scenario file, the default
+    // configuration when missing
+    #[serde(default)]
+    pub config: Option<String>,
+    // Simulated time to reach every goal, the scenario is lost after it
+    pub time_limit_ms: u64,
+    pub goals: Vec<Goal>,
+    #[serde(default)]
+    pub lose: Vec<LoseCondition>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "goal", rename_all = "snake_case")]
+pub enum Goal {
+    // Units delivered to the base since the start of the scenario
+    Collect {
+        #[serde(default)]
+        energy: usize,
+        #[serde(default)]
+        ore: usize,
+        #[serde(default)]
+        science: usize,
+    },
+    // Share of the map revealed, between 0 and 1
+    Explore {
+        ratio: f64,
+    },
+    // Robots deployed at the same time
+    Robots {
+        count: usize,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "condition", rename_all = "snake_case")]
+pub enum LoseCondition {
+    // Robots waiting for a rescue at the same time
+    Stranded { robots: usize },
+}
+
+impl Scenario {
+    // Read a scenario file, its configuration tables are merged over the referenced
+    // configuration file
+    pub fn load(path: &str) -> Result<(Scenario, Config), Box<dyn Error>> {
+        let mut overrides: toml::Table = fs::read_to_string(path)?.parse()?;
+        let scenario: Scenario = overrides
+            .remove("scenario")
+            .ok_or_else(|| format!("{} : table [scenario] manquante", path))?
+            .try_into()?;
+        if scenario.goals.is_empty() {
+            return Err(format!("{} : le scénario n'a aucun objectif", path).into());
+        }
+
+        let config_path = match &scenario.config {
+            Some(config) => Path::new(path)
+                .parent()
+                .unwrap_or(Path::new(""))
+                .join(config),
+            None => PathBuf::from(DEFAULT_CONFIG_PATH),
+        };
+        let mut config: toml::Table = fs::read_to_string(&config_path)
+            .map_err(|error| format!("{} : {}", config_path.display(), error))?
+            .parse()?;
+        merge(&mut config, overrides);
+        let config: Config = toml::Value::Table(config).try_into()?;
+        config.check()?;
+        Ok((scenario, config))
+    }
+}
+
+// Replace the values of `target` by those of `overrides`, table by table
+fn merge(target: &mut toml::Table, overrides: toml::Table) {
+    for (key, value) in overrides {
+        match (target.get_mut(&key), value) {
+            (Some(toml::Value::Table(target)), toml::Value::Table(value)) => merge(target, value),
+            (_, value) => {
+                target.insert(key, value);
+            }
+        }
+    }
+}
+
+impl Goal {
+    // Between 0 for nothing done and 1 for a reached goal
+    fn progress(&self, delivered: (usize, usize, usize), simulation: &Simulation) -> f64 {
+        let ratio = |done: usize, wanted: usize| {
+            if wanted == 0 {
+                1.0
+            } else {
+                (done as f64 / wanted as f64).min(1.0)
+            }
+        };
+        match self {
+            Goal::Collect {
+                energy,
+                ore,
+                science,
+            } => {
+                let wanted = [
+                    (delivered.0, *energy),
+                    (delivered.1, *ore),
+                    (delivered.2, *science),
+                ];
+                let total: usize = wanted.iter().map(|(_, wanted)| wanted).sum();
+                if total == 0 {
+                    return 1.0;
+                }
+                // Each resource weighs as much as the units asked for it
+                wanted
+                    .iter()
+                    .map(|(done, wanted)| ratio(*done, *wanted) * *wanted as f64)
+                    .sum::<f64>()
+                    / total as f64
+            }
+            Goal::Explore { ratio: wanted } => {
+                let explored = simulation.base.explored_ratio();
+                if *wanted <= 0.0 {
+                    1.0
+                } else {
+                    (explored / wanted).min(1.0)
+                }
+            }
+            Goal::Robots { count } => {
+                let (explorers, collectors) = simulation.base.robot_counts();
+                ratio(explorers + collectors, *count)
+            }
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            Goal::Collect {
+                energy,
+                ore,
+                science,
+            } => {
+                let amounts: Vec<String> =
+                    [(energy, "énergie"), (ore, "minerai"), (science, "science")]
+                        .iter()
+                        .filter(|(amount, _)| **amount > 0)
+                        .map(|(amount, resource)| format!("{} {}", amount, resource))
+                        .collect();
+                format!("collecter {}", amounts.join(", "))
+            }
+            Goal::Explore { ratio } => format!("explorer {:.0}% de la carte", ratio * 100.0),
+            Goal::Robots { count } => format!("déployer {} robots", count),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    pub won: bool,
+    // Ticks since the start of the scenario
+    pub ticks: u64,
+    pub reason: String,
+    pub score: u64,
+}
+
+// Follows a simulation until every goal is reached or a lose condition happens
+pub struct ScenarioRun {
+    scenario: Scenario,
+    start_tick: u64,
+    limit_ticks: u64,
+    // Energy, ore and science delivered since the start
+    delivered: (usize, usize, usize),
+    // Ticks at which each goal was first reached
+    reached: Vec<Option<u64>>,
+    outcome: Option<Outcome>,
+}
+
+impl ScenarioRun {
+    pub fn new(scenario: Scenario, simulation: &Simulation) -> Self {
+        let tick_ms = simulation.tick_duration().as_millis().max(1) as u64;
+        ScenarioRun {
+            limit_ticks: (scenario.time_limit_ms / tick_ms).max(1),
+            reached: vec![None; scenario.goals.len()],
+            scenario,
+            start_tick: simulation.tick,
+            delivered: (0, 0, 0),
+            outcome: None,
+        }
+    }
+
+    pub fn scenario(&self) -> &Scenario {
+        &self.scenario
+    }
+
+    pub fn outcome(&self) -> Option<&Outcome> {
+        self.outcome.as_ref()
+    }
+
+    // Account for the last step, returns the outcome once the scenario has ended
+    pub fn observe(&mut self, simulation: &Simulation) -> Option<&Outcome> {
+        if self.outcome.is_some() {
+            return self.outcome.as_ref();
+        }
+
+        for timed in simulation.events() {
+            if let Event::ResourceDelivered {
+                resource, amount, ..
+            } = &timed.event
+            {
+                match resource {
+                    TypeCase::Energy => self.delivered.0 += amount,
+                    TypeCase::Ore => self.delivered.1 += amount,
+                    TypeCase::Science => self.delivered.2 += amount,
+                    _ => {}
+                }
+            }
+        }
+
+        let ticks = simulation.tick - self.start_tick;
+        let mut progress = self.progress(simulation);
+        for (reached, progress) in self.reached.iter_mut().zip(progress.iter_mut()) {
+            if *progress >= 1.0 {
+                *progress = 1.0;
+                reached.get_or_insert(ticks);
+            }
+        }
+
+        let stranded = simulation
+            .base
+            .deployed_robots
+            .iter()
+            .filter(|robot| robot.get_state() == RobotState::Stranded)
+            .count();
+        let lost = self
+            .scenario
+            .lose
+            .iter()
+            .find_map(|condition| match condition {
+                LoseCondition::Stranded { robots } if stranded >= *robots => {
+                    Some(format!("{} robots en panne", stranded))
+                }
+                _ => None,
+            });
+
+        let (won, reason) = if self.reached.iter().all(Option::is_some) {
+            (true, "tous les objectifs sont atteints".to_string())
+        } else if let Some(reason) = lost {
+            (false, reason)
+        } else if ticks >= self.limit_ticks {
+            (false, "temps écoulé".to_string())
+        } else {
+            return None;
+        };
+
+        self.outcome = Some(Outcome {
+            won,
+            ticks,
+            score: self.score(&progress, won, ticks),
+            reason,
+        });
+        self.outcome.as_ref()
+    }
+
+    // Progress of each goal, a goal reached once stays reached
+    pub fn progress(&self, simulation: &Simulation) -> Vec<f64> {
+        self.scenario
+            .goals
+            .iter()
+            .zip(&self.reached)
+            .map(|(goal, reached)| match reached {
+                Some(_) => 1.0,
+                None => goal.progress(self.delivered, simulation),
+            })
+            .collect()
+    }
+
+    // Points for the progress of each goal, plus a bonus for the time left when won
+    fn score(&self, progress: &[f64], won: bool, ticks: u64) -> u64 {
+        let goals: f64 = progress.iter().map(|progress| progress * GOAL_POINTS).sum();
+        let time = if won {
+            let left = self.limit_ticks.saturating_sub(ticks) as f64;
+            TIME_POINTS * left / self.limit_ticks as f64
+        } else {
+            0.0
+        };
+        (goals + time).round() as u64
+    }
+
+    // Lines describing the result and each goal
+    pub fn report(&self, simulation: &Simulation) -> Vec<String> {
+        let mut lines = vec![format!("Scénario : {}", self.scenario.name)];
+        if let Some(outcome) = &self.outcome {
+            lines.push(format!(
+                "{} après {} ticks ({}), score {}",
+                if outcome.won { "Réussite" } else { "Échec" },
+                outcome.ticks,
+                outcome.reason,
+                outcome.score
+            ));
+        }
+        let progress = self.progress(simulation);
+        for ((goal, reached), progress) in
+            self.scenario.goals.iter().zip(&self.reached).zip(progress)
+        {
+            let state = match reached {
+                Some(ticks) => format!("atteint au tick {}", ticks),
+                None => format!("{:.0}%", progress * 100.0),
+            };
+            lines.push(format!("- {} : {}", goal.label(), state));
+        }
+        lines
+    }
+}
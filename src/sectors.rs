@@ -0,0 +1,56 @@
+use crate::config::SectorsConfig;
+
+// Rectangle of the map given to an explorer, from (x0, y0) included to (x1, y1) excluded
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sector {
+    pub x0: usize,
+    pub y0: usize,
+    pub x1: usize,
+    pub y1: usize,
+}
+
+impl Sector {
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.x0 && x < self.x1 && y >= self.y0 && y < self.y1
+    }
+
+    // Number of moves from a case to the closest case of the sector, ignoring the walls
+    pub fn distance(&self, x: usize, y: usize) -> usize {
+        let dx = if x < self.x0 {
+            self.x0 - x
+        } else {
+            x.saturating_sub(self.x1 - 1)
+        };
+        let dy = if y < self.y0 {
+            self.y0 - y
+        } else {
+            y.saturating_sub(self.y1 - 1)
+        };
+        dx + dy
+    }
+
+    pub fn center(&self) -> (usize, usize) {
+        ((self.x0 + self.x1) / 2, (self.y0 + self.y1) / 2)
+    }
+}
+
+// Fixed grid of sectors covering the map, row by row, empty sectors are left out
+pub fn grid(width: usize, height: usize, config: &SectorsConfig) -> Vec<Sector> {
+    let columns = config.columns.clamp(1, width.max(1));
+    let rows = config.rows.clamp(1, height.max(1));
+    let mut sectors = Vec::new();
+    for row in 0..rows {
+        for column in 0..columns {
+            let sector = Sector {
+                x0: column * width / columns,
+                y0: row * height / rows,
+                x1: (column + 1) * width / columns,
+                y1: (row + 1) * height / rows,
+            };
+            if sector.x1 > sector.x0 && sector.y1 > sector.y0 {
+                sectors.push(sector);
+            }
+        }
+    }
+    sectors
+}
@@ -0,0 +1,173 @@
+use crate::base::Base;
+use crate::config::SelectionConfig;
+use crate::generation::TypeCase;
+use crate::pathfinding::{DistanceField, Pathfinder};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+// Picks the next resource a collector leaving the base should reserve
+pub trait ResourceSelector {
+    fn select(&self, base: &Base) -> Option<(usize, usize)>;
+}
+
+pub fn from_config(config: &SelectionConfig) -> Box<dyn ResourceSelector> {
+    match config {
+        SelectionConfig::StockPriority => Box::new(StockPrioritySelector),
+        SelectionConfig::NearestPath => Box::new(NearestPathSelector),
+        SelectionConfig::CostWeighted => Box::new(CostWeightedSelector),
+        SelectionConfig::RoundRobin => Box::new(RoundRobinSelector),
+    }
+}
+
+// Sort resource by priority level
+#[derive(Clone, Debug, Eq)]
+struct PrioritizedResource {
+    x: usize,
+    y: usize,
+    distance: usize,
+    priority_level: usize,
+}
+
+// Implentation of necessary traits for the priority queue
+impl PartialEq for PrioritizedResource {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority_level == other.priority_level && self.distance == other.distance
+    }
+}
+
+// Reverse the order so that the most prioritized and closest resources are at the beginning
+impl PartialOrd for PrioritizedResource {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PrioritizedResource {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Compare the priority level first
+        match self.priority_level.cmp(&other.priority_level) {
+            Ordering::Equal => {}
+            ordering => return ordering,
+        }
+
+        // Then compare the distance
+        other.distance.cmp(&self.distance)
+    }
+}
+
+// The resource furthest below the highest stock first, then the closest as the crow flies
+pub struct StockPrioritySelector;
+
+impl ResourceSelector for StockPrioritySelector {
+    fn select(&self, base: &Base) -> Option<(usize, usize)> {
+        // Find the highest resource counter
+        let max_resource_count = base.energy.max(base.ore).max(base.science);
+
+        let mut priority_queue: BinaryHeap<PrioritizedResource> = BinaryHeap::new();
+        for (x, y, case) in base.available_resources() {
+            // Calculate the priority level based on the difference with the highest counter
+            let priority_level = max_resource_count.saturating_sub(base.stock(&case));
+            priority_queue.push(PrioritizedResource {
+                x,
+                y,
+                distance: manhattan_distance((base.position_x, base.position_y), (x, y)),
+                priority_level,
+            });
+        }
+
+        // Take the most prioritized resource
+        priority_queue
+            .pop()
+            .map(|resource| (resource.x, resource.y))
+    }
+}
+
+// The resource with the cheapest path from the base, whatever its type
+pub struct NearestPathSelector;
+
+impl ResourceSelector for NearestPathSelector {
+    fn select(&self, base: &Base) -> Option<(usize, usize)> {
+        let distances = base_distances(base);
+        base.available_resources()
+            .into_iter()
+            .filter_map(|(x, y, _)| distances.cost(x, y).map(|cost| (cost, x, y)))
+            .min()
+            .map(|(_, x, y)| (x, y))
+    }
+}
+
+// Path cost divided by how much the resource is missing to build one robot of each kind
+pub struct CostWeightedSelector;
+
+impl ResourceSelector for CostWeightedSelector {
+    fn select(&self, base: &Base) -> Option<(usize, usize)> {
+        let robots = &base.get_config().robots;
+        let needed = |case: &TypeCase| match case {
+            TypeCase::Energy => robots.explorer.cost_energy + robots.collector.cost_energy,
+            TypeCase::Ore => robots.explorer.cost_ore + robots.collector.cost_ore,
+            TypeCase::Science => robots.explorer.cost_science + robots.collector.cost_science,
+            _ => 0,
+        };
+
+        let distances = base_distances(base);
+        let mut best: Option<(f64, usize, usize)> = None;
+        for (x, y, case) in base.available_resources() {
+            let Some(cost) = distances.cost(x, y) else {
+                continue;
+            };
+            let weight = 1 + needed(&case).saturating_sub(base.stock(&case));
+            let score = cost as f64 / weight as f64;
+            if best.is_none_or(|(best_score, _, _)| score < best_score) {
+                best = Some((score, x, y));
+            }
+        }
+        best.map(|(_, x, y)| (x, y))
+    }
+}
+
+// Energy, ore and science in turn, the cheapest by path of the next type available
+pub struct RoundRobinSelector;
+
+const RESOURCE_CYCLE: [TypeCase; 3] = [TypeCase::Energy, TypeCase::Ore, TypeCase::Science];
+
+impl ResourceSelector for RoundRobinSelector {
+    fn select(&self, base: &Base) -> Option<(usize, usize)> {
+        let first = match base.last_reserved() {
+            Some(case) => RESOURCE_CYCLE
+                .iter()
+                .position(|c| c == case)
+                .map_or(0, |i| i + 1),
+            None => 0,
+        };
+
+        let distances = base_distances(base);
+        let resources = base.available_resources();
+        (0..RESOURCE_CYCLE.len())
+            .map(|offset| &RESOURCE_CYCLE[(first + offset) % RESOURCE_CYCLE.len()])
+            .find_map(|kind| {
+                resources
+                    .iter()
+                    .filter(|(_, _, case)| case == kind)
+                    .filter_map(|&(x, y, _)| distances.cost(x, y).map(|cost| (cost, x, y)))
+                    .min()
+                    .map(|(_, x, y)| (x, y))
+            })
+    }
+}
+
+// The distance field of the base, computed here when the base has none yet
+fn base_distances(base: &Base) -> Cow<'_, DistanceField> {
+    match base.computed_distance_field() {
+        Some(field) => Cow::Borrowed(field),
+        None => Cow::Owned(DistanceField::new(
+            (base.position_x, base.position_y),
+            &base.known_map,
+            &Pathfinder::from_config(&base.get_config().pathfinding),
+        )),
+    }
+}
+
+fn manhattan_distance(a: (usize, usize), b: (usize, usize)) -> usize {
+    a.0.abs_diff(b.0) + a.1.abs_diff(b.1)
+}
@@ -0,0 +1,162 @@
+use crate::base::Base;
+use crate::config::Config;
+use crate::events::TimedEvent;
+use crate::generation::{generate_map, World};
+use crate::regeneration;
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+// Random generator of the simulation, serializable so that saved games resume identically
+pub type SimRng = ChaCha12Rng;
+
+// Counts the ticks left before an entity acting every `interval` ticks can act again
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Cadence {
+    interval: u64,
+    remaining: u64,
+}
+
+impl Cadence {
+    pub fn new(interval: u64) -> Self {
+        Cadence {
+            interval: interval.max(1),
+            remaining: 0,
+        }
+    }
+
+    // Returns true once every `interval` calls, starting with the first one
+    pub fn ready(&mut self) -> bool {
+        if self.remaining == 0 {
+            self.remaining = self.interval - 1;
+            true
+        } else {
+            self.remaining -= 1;
+            false
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Simulation {
+    pub base: Base,
+    pub tick: u64,
+    rng: SimRng,
+    production: Cadence,
+    tick_ms: u64,
+    // Events of the last step
+    #[serde(skip)]
+    events: Vec<TimedEvent>,
+}
+
+impl Simulation {
+    pub fn new(width: usize, height: usize, config: Config) -> Self {
+        let world = generate_map(width, height, config.map.seed, &config);
+        Self::from_world(world, config)
+    }
+
+    // Start from a world built elsewhere, such as a map file
+    pub fn from_world(world: World, config: Config) -> Self {
+        let seed = config.map.seed;
+        let (base_x, base_y) = world.base;
+
+        let tick_ms = config.simulation.tick_ms.max(1);
+        let production = Cadence::new(
+            config
+                .simulation
+                .ticks_for(config.base.production_interval_ms),
+        );
+
+        // The robots use their own random stream so that the map generation stays untouched
+        let rng = SimRng::seed_from_u64((seed as u64) ^ 0x5EED_5EED);
+
+        let mut base = Base::new(
+            base_x,
+            base_y,
+            world.real_map,
+            world.known_map,
+            world.deposits,
+            config,
+        );
+        // The initial robots are part of the starting state, not events of the first tick
+        base.events.clear();
+
+        Simulation {
+            base,
+            tick: 0,
+            rng,
+            production,
+            tick_ms,
+            events: Vec::new(),
+        }
+    }
+
+    // Advance the whole world by one tick: the regrowing resources, the base, then every robot
+    // in deployment order
+    pub fn step(&mut self) {
+        regeneration::regenerate(&mut self.base, self.tick, &mut self.rng);
+
+        if self.production.ready() {
+            self.base.produce_robots();
+        }
+
+        let mut robots = std::mem::take(&mut self.base.deployed_robots);
+        for robot in robots.iter_mut() {
+            robot.update(&mut self.base, &mut self.rng);
+        }
+        self.base.deployed_robots = robots;
+
+        let tick = self.tick;
+        self.events = self
+            .base
+            .events
+            .drain(..)
+            .map(|event| TimedEvent { tick, event })
+            .collect();
+
+        self.tick += 1;
+    }
+
+    pub fn events(&self) -> &[TimedEvent] {
+        &self.events
+    }
+
+    pub fn tick_duration(&self) -> Duration {
+        Duration::from_millis(self.tick_ms)
+    }
+}
+
+// Helpers shared by the tests of the simulation and of the saves
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // Simulation of the shipped configuration with another seed
+    pub(crate) fn simulation(seed: u32) -> Simulation {
+        let mut config = Config::load_from(concat!(env!("CARGO_MANIFEST_DIR"), "/config.toml"))
+            .expect("the shipped configuration loads");
+        config.map.seed = seed;
+        Simulation::new(60, 30, config)
+    }
+
+    // Whole state of the simulation, equal for equal simulations
+    pub(crate) fn state(simulation: &Simulation) -> String {
+        serde_json::to_string(simulation).expect("the simulation serializes")
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_simulation() {
+        let mut first = simulation(7);
+        let mut second = simulation(7);
+        for _ in 0..6 {
+            for _ in 0..500 {
+                first.step();
+                second.step();
+            }
+            assert_eq!(first.tick, second.tick);
+            assert_eq!(state(&first), state(&second));
+        }
+        assert_ne!(state(&first), state(&simulation(8)));
+    }
+}
@@ -0,0 +1,115 @@
+use crate::events::Event;
+use crate::generation::TypeCase;
+use crate::simulation::Simulation;
+use serde::Serialize;
+use std::collections::VecDeque;
+
+// Simulated time between two samples
+const SAMPLE_MS: u64 = 1000;
+// Oldest samples are dropped past this count (one hour at one sample per second)
+const MAX_SAMPLES: usize = 3600;
+const MINUTE_MS: u64 = 60_000;
+
+// State of the simulation at one point in time
+#[derive(Clone, Debug, Serialize)]
+pub struct Sample {
+    pub tick: u64,
+    pub energy: usize,
+    pub ore: usize,
+    pub science: usize,
+    pub explorers: usize,
+    pub collectors: usize,
+    // Between 0 and 1
+    pub explored: f64,
+    // Counters since the beginning of the observation
+    pub delivered: usize,
+    pub explorers_built: usize,
+    pub collectors_built: usize,
+}
+
+// Samples of the simulation taken at a regular interval, for the charts
+pub struct History {
+    tick_ms: u64,
+    sample_ticks: u64,
+    samples: VecDeque<Sample>,
+    delivered: usize,
+    explorers_built: usize,
+    collectors_built: usize,
+}
+
+impl History {
+    pub fn new(simulation: &Simulation) -> Self {
+        let tick_ms = simulation.tick_duration().as_millis().max(1) as u64;
+        let mut history = History {
+            tick_ms,
+            sample_ticks: (SAMPLE_MS / tick_ms).max(1),
+            samples: VecDeque::new(),
+            delivered: 0,
+            explorers_built: 0,
+            collectors_built: 0,
+        };
+        history.push_sample(simulation);
+        history
+    }
+
+    // Count the events of the last step and take a sample when one is due
+    pub fn observe(&mut self, simulation: &Simulation) {
+        for timed in simulation.events() {
+            match &timed.event {
+                Event::ResourceDelivered { amount, .. } => self.delivered += amount,
+                Event::RobotSpawned { kind, .. } => match kind {
+                    TypeCase::Explorer => self.explorers_built += 1,
+                    TypeCase::Collector => self.collectors_built += 1,
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+
+        if simulation.tick.is_multiple_of(self.sample_ticks) {
+            self.push_sample(simulation);
+        }
+    }
+
+    fn push_sample(&mut self, simulation: &Simulation) {
+        let base = &simulation.base;
+        let (explorers, collectors) = base.robot_counts();
+        self.samples.push_back(Sample {
+            tick: simulation.tick,
+            energy: base.energy,
+            ore: base.ore,
+            science: base.science,
+            explorers,
+            collectors,
+            explored: base.explored_ratio(),
+            delivered: self.delivered,
+            explorers_built: self.explorers_built,
+            collectors_built: self.collectors_built,
+        });
+        if self.samples.len() > MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+    }
+
+    pub fn samples(&self) -> &VecDeque<Sample> {
+        &self.samples
+    }
+
+    pub fn last(&self) -> Option<&Sample> {
+        self.samples.back()
+    }
+
+    // Seconds of simulated time of a sample, for the x axis of the charts
+    pub fn seconds(&self, sample: &Sample) -> f64 {
+        (sample.tick * self.tick_ms) as f64 / 1000.0
+    }
+
+    // Resources delivered during the minute before each sample
+    pub fn collection_rates(&self) -> Vec<u64> {
+        let window = (MINUTE_MS / SAMPLE_MS) as usize;
+        let delivered: Vec<usize> = self.samples.iter().map(|s| s.delivered).collect();
+        (0..delivered.len())
+            .map(|i| (delivered[i] - delivered[i.saturating_sub(window)]) as u64)
+            .collect()
+    }
+}
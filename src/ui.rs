@@ -1,22 +1,122 @@
-use crate::generation::TypeCase;
-use crate::robot::Robot;
+use crate::camera::{Camera, Viewport};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
 use ratatui::backend::CrosstermBackend;
-use ratatui::layout::{Constraint, Direction, Layout};
-use ratatui::style::{Color, Style};
-use ratatui::widgets::{Block, Borders, Paragraph};
-use ratatui::Terminal;
-use std::io;
-use std::sync::{Arc, Mutex};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::symbols;
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::{
+    Axis, Block, Borders, Chart, Dataset, GraphType, Paragraph, Row, Sparkline, Table, TableState,
+};
+use ratatui::{Frame, Terminal};
+use rust_ereea::{Base, History, Map, RobotState, RobotStatus, Sample, TypeCase};
+use std::io::{self, Stdout};
+
+pub type Tui = Terminal<CrosstermBackend<Stdout>>;
+
+const SIDE_PANEL_WIDTH: u16 = 56;
+
+// What the interface draws, built from the running simulation or from a replay
+pub struct Scene<'a> {
+    pub known_map: &'a Map,
+    pub robots: Vec<RobotStatus>,
+    pub base_x: usize,
+    pub base_y: usize,
+}
+
+impl<'a> Scene<'a> {
+    pub fn from_base(base: &'a Base) -> Self {
+        let (base_x, base_y) = base.position();
+        Scene {
+            known_map: base.known_map(),
+            robots: base.robots().iter().map(|robot| robot.status()).collect(),
+            base_x,
+            base_y,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.known_map[0].len()
+    }
+
+    pub fn height(&self) -> usize {
+        self.known_map.len()
+    }
+}
+
+// Switch the terminal to raw mode on the alternate screen so that keys are read one by one
+pub fn init_terminal() -> Result<Tui, io::Error> {
+    // Give the terminal back before printing the panic message
+    let panic_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = disable_raw_mode();
+        let _ = execute!(io::stdout(), LeaveAlternateScreen);
+        panic_hook(info);
+    }));
+
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    Terminal::new(CrosstermBackend::new(io::stdout()))
+}
+
+pub fn restore_terminal(terminal: &mut Tui) -> Result<(), io::Error> {
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()
+}
+
+// Raw mode turns Ctrl-C into a key press, it quits like q
+pub fn is_quit_key(key: &KeyEvent) -> bool {
+    match key.code {
+        KeyCode::Char('q') => true,
+        KeyCode::Char('c') => key.modifiers.contains(KeyModifiers::CONTROL),
+        _ => false,
+    }
+}
+
+// Panels displayed next to the map, toggled with the keyboard
+pub struct Panels {
+    pub inspector: bool,
+    pub stats: bool,
+}
+
+impl Default for Panels {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Panels {
+    pub fn new() -> Self {
+        Panels {
+            inspector: true,
+            stats: false,
+        }
+    }
+
+    // Apply a key press, returns false when the key does not toggle a panel
+    pub fn handle_key(&mut self, code: KeyCode) -> bool {
+        match code {
+            KeyCode::Char('i') => self.inspector = !self.inspector,
+            KeyCode::Char('g') => self.stats = !self.stats,
+            _ => return false,
+        }
+        true
+    }
+}
 
 pub fn run_ui(
-    map: &Arc<Mutex<Vec<Vec<TypeCase>>>>,
+    terminal: &mut Tui,
+    scene: &Scene,
     resources: &str,
-    robots: &Arc<Mutex<Vec<Box<dyn Robot + Send>>>>,
+    camera: &Camera,
+    panels: &Panels,
+    history: Option<&History>,
 ) -> Result<(), io::Error> {
-    let stdout = io::stdout();
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
-
     terminal.draw(|f| {
         let size = f.area();
         let reduced_height = size.height.saturating_sub(5);
@@ -27,67 +127,389 @@ pub fn run_ui(
 
         let resources_paragraph = Paragraph::new(resources)
             .block(Block::default().borders(Borders::ALL).title("Resources"))
-            .style(
-                Style::default()
-                    .fg(Color::Rgb(208, 191, 154))
-                    .bg(Color::Rgb(27, 27, 34)),
-            );
+            .style(base_style());
 
         f.render_widget(resources_paragraph, chunks[0]);
 
-        // Create a copy of the map for display
-        let map_guard = map.lock().unwrap();
-        let mut displayed_map = (*map_guard).clone();
-        drop(map_guard);
-
-        // Update the map with the robots' positions
-        if let Ok(robots_guard) = robots.lock() {
-            for robot in robots_guard.iter() {
-                let x = robot.get_position_x();
-                let y = robot.get_position_y();
-                if y < displayed_map.len() && x < displayed_map[0].len() {
-                    if robot.get_type() == TypeCase::Collector {
-                        if let Ok(map_guard) = map.lock() {
-                            if map_guard[y][x] != TypeCase::Base {
-                                displayed_map[y][x] = robot.get_type();
-                            }
-                        }
-                    } else {
-                        displayed_map[y][x] = robot.get_type();
-                    }
-                }
+        // The side column holds the inspector and the stats, stacked when both are shown
+        let history = history.filter(|_| panels.stats);
+        let mut map_area = chunks[1];
+        if panels.inspector || history.is_some() {
+            let columns = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(0), Constraint::Length(SIDE_PANEL_WIDTH)])
+                .split(chunks[1]);
+            map_area = columns[0];
+
+            let shown = panels.inspector as u32 + history.is_some() as u32;
+            let rows = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints(vec![Constraint::Ratio(1, shown); shown as usize])
+                .split(columns[1]);
+            if panels.inspector {
+                render_inspector(f, rows[0], &scene.robots, camera.selected_robot);
+            }
+            if let Some(history) = history {
+                render_stats(f, rows[rows.len() - 1], history);
             }
         }
 
-        let mut map_string = String::new();
-        for row in displayed_map.iter() {
-            for case in row {
-                let symbol = match case {
-                    TypeCase::Void => "  ",
-                    TypeCase::Wall => "🪨",
-                    TypeCase::Energy => "⚡",
-                    TypeCase::Ore => "💎",
-                    TypeCase::Science => "🧪",
-                    TypeCase::Base => "🏠",
-                    TypeCase::Explorer => "🛸",
-                    TypeCase::Collector => "🤖",
-                    TypeCase::Unknown => "▒▒",
-                };
-                map_string.push_str(symbol);
-            }
-            map_string.push('\n');
+        // Only the inside of the borders is available for the map
+        let viewport = camera.viewport(
+            scene.width(),
+            scene.height(),
+            map_area.width.saturating_sub(2) as usize,
+            map_area.height.saturating_sub(2) as usize,
+        );
+        let selected = scene
+            .robots
+            .get(camera.selected_robot)
+            .map(|robot| (robot.x, robot.y));
+        let map_text = render_map(scene.known_map, &scene.robots, &viewport, selected);
+
+        let mut title = format!("Carte ({}, {})", viewport.x, viewport.y);
+        if viewport.condensed {
+            title.push_str(&format!(" zoom 1:{}", viewport.scale));
+        }
+        if camera.following {
+            title.push_str(&format!(" - suivi du robot #{}", camera.selected_robot));
         }
 
-        let map_paragraph = Paragraph::new(map_string)
-            .block(Block::default().borders(Borders::ALL).title("Carte"))
-            .style(
-                Style::default()
-                    .fg(Color::Rgb(208, 191, 154))
-                    .bg(Color::Rgb(27, 27, 34)),
-            );
+        let map_paragraph = Paragraph::new(map_text)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .style(base_style());
 
-        f.render_widget(map_paragraph, chunks[1]);
+        f.render_widget(map_paragraph, map_area);
     })?;
 
     Ok(())
 }
+
+fn base_style() -> Style {
+    Style::default()
+        .fg(Color::Rgb(208, 191, 154))
+        .bg(Color::Rgb(27, 27, 34))
+}
+
+fn highlight_style() -> Style {
+    Style::default()
+        .fg(Color::Rgb(27, 27, 34))
+        .bg(Color::Rgb(208, 191, 154))
+}
+
+fn render_inspector(f: &mut Frame, area: Rect, robots: &[RobotStatus], selected: usize) {
+    let header = Row::new(["#", "Type", "État", "Cible", "Charge", "Chemin", "Batt."])
+        .style(Style::default().add_modifier(Modifier::BOLD));
+    let rows = robots.iter().map(|robot| {
+        let target = match (robot.state, robot.target) {
+            (RobotState::Returning, _) => "base".to_string(),
+            (_, Some((x, y))) => format!("{},{}", x, y),
+            (_, None) => "-".to_string(),
+        };
+        let carried = robot
+            .carried
+            .as_ref()
+            .map_or("-".to_string(), |resource| format!("{:?}", resource));
+        Row::new([
+            robot.id.to_string(),
+            format!("{:?}", robot.kind),
+            state_label(robot.state).to_string(),
+            target,
+            carried,
+            robot.path_length.to_string(),
+            robot
+                .battery
+                .map_or("-".to_string(), |percent| format!("{}%", percent)),
+        ])
+    });
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(3),
+            Constraint::Length(9),
+            Constraint::Length(9),
+            Constraint::Length(7),
+            Constraint::Length(7),
+            Constraint::Length(6),
+            Constraint::Length(5),
+        ],
+    )
+    .header(header)
+    .block(Block::default().borders(Borders::ALL).title("Robots"))
+    .style(base_style())
+    .row_highlight_style(highlight_style());
+
+    // The table scrolls by itself to keep the selected robot visible
+    let mut state = TableState::default().with_selected(Some(selected));
+    f.render_stateful_widget(table, area, &mut state);
+}
+
+fn render_stats(f: &mut Frame, area: Rect, history: &History) {
+    let block = Block::default().borders(Borders::ALL).title("Statistiques");
+    let inner = block.inner(area);
+    f.render_widget(block.style(base_style()), area);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(2),
+            Constraint::Min(6),
+            Constraint::Min(5),
+            Constraint::Length(4),
+            Constraint::Length(4),
+        ])
+        .split(inner);
+
+    let Some(last) = history.last() else {
+        return;
+    };
+    let summary = format!(
+        "Construits : {} explorateurs, {} collecteurs\nExploré : {:.1}%",
+        last.explorers_built,
+        last.collectors_built,
+        last.explored * 100.0
+    );
+    f.render_widget(Paragraph::new(summary).style(base_style()), rows[0]);
+
+    // Stock of each resource over the samples that fit in the chart
+    let visible = history
+        .samples()
+        .len()
+        .saturating_sub(inner.width.saturating_sub(8) as usize);
+    let samples: Vec<_> = history.samples().iter().skip(visible).collect();
+    let points = |value: fn(&Sample) -> usize| -> Vec<(f64, f64)> {
+        samples
+            .iter()
+            .map(|sample| (history.seconds(sample), value(sample) as f64))
+            .collect()
+    };
+    let energy = points(|sample| sample.energy);
+    let ore = points(|sample| sample.ore);
+    let science = points(|sample| sample.science);
+    let explorers_built = points(|sample| sample.explorers_built);
+    let collectors_built = points(|sample| sample.collectors_built);
+
+    let start = samples
+        .first()
+        .map_or(0.0, |sample| history.seconds(sample));
+    let end = history.seconds(last).max(start + 1.0);
+    let time_axis = || {
+        Axis::default()
+            .bounds([start, end])
+            .labels([format!("{:.0}s", start), format!("{:.0}s", end)])
+    };
+    let max_stock = samples
+        .iter()
+        .map(|sample| sample.energy.max(sample.ore).max(sample.science))
+        .max()
+        .unwrap_or(0)
+        .max(1) as f64;
+
+    let chart = Chart::new(vec![
+        line_dataset("énergie", Color::Yellow, &energy),
+        line_dataset("minerai", Color::Cyan, &ore),
+        line_dataset("science", Color::Magenta, &science),
+    ])
+    .x_axis(time_axis())
+    .y_axis(
+        Axis::default()
+            .bounds([0.0, max_stock])
+            .labels(["0".to_string(), format!("{}", max_stock)]),
+    )
+    .style(base_style());
+    f.render_widget(chart, rows[1]);
+
+    // Robots built of each type since the beginning of the observation
+    let max_built = samples
+        .iter()
+        .map(|sample| sample.explorers_built.max(sample.collectors_built))
+        .max()
+        .unwrap_or(0)
+        .max(1) as f64;
+    let built_chart = Chart::new(vec![
+        line_dataset("explorateurs", Color::Green, &explorers_built),
+        line_dataset("collecteurs", Color::Red, &collectors_built),
+    ])
+    .block(Block::default().title("Robots construits"))
+    .x_axis(time_axis())
+    .y_axis(
+        Axis::default()
+            .bounds([0.0, max_built])
+            .labels(["0".to_string(), format!("{}", max_built)]),
+    )
+    .style(base_style());
+    f.render_widget(built_chart, rows[2]);
+
+    let width = inner.width as usize;
+    let rates = history.collection_rates();
+    let rates = &rates[rates.len().saturating_sub(width)..];
+    let rate_title = format!(
+        "Collecte : {}/min",
+        rates.last().copied().unwrap_or_default()
+    );
+    let rate_sparkline = Sparkline::default()
+        .block(Block::default().title(rate_title))
+        .data(rates)
+        .style(base_style().fg(Color::Green));
+    f.render_widget(rate_sparkline, rows[3]);
+
+    let explored: Vec<u64> = samples
+        .iter()
+        .skip(samples.len().saturating_sub(width))
+        .map(|sample| (sample.explored * 100.0) as u64)
+        .collect();
+    let explored_sparkline = Sparkline::default()
+        .block(Block::default().title("Exploration (%)"))
+        .data(&explored)
+        .max(100)
+        .style(base_style().fg(Color::Blue));
+    f.render_widget(explored_sparkline, rows[4]);
+}
+
+fn line_dataset<'a>(name: &'static str, color: Color, data: &'a [(f64, f64)]) -> Dataset<'a> {
+    Dataset::default()
+        .name(name)
+        .marker(symbols::Marker::Braille)
+        .graph_type(GraphType::Line)
+        .style(Style::default().fg(color))
+        .data(data)
+}
+
+fn state_label(state: RobotState) -> &'static str {
+    match state {
+        RobotState::Idle => "inactif",
+        RobotState::Exploring => "explore",
+        RobotState::Travelling => "en route",
+        RobotState::Collecting => "collecte",
+        RobotState::Returning => "retour",
+        RobotState::Recharging => "recharge",
+        RobotState::Stranded => "en panne",
+    }
+}
+
+fn render_map(
+    map: &Map,
+    robots: &[RobotStatus],
+    viewport: &Viewport,
+    selected: Option<(usize, usize)>,
+) -> Text<'static> {
+    // Create a copy of the visible part of the map for display
+    let mut displayed_map: Vec<Vec<TypeCase>> = map[viewport.y..viewport.y + viewport.height]
+        .iter()
+        .map(|row| row[viewport.x..viewport.x + viewport.width].to_vec())
+        .collect();
+
+    // Update the map with the robots' positions
+    for robot in robots.iter() {
+        let (x, y) = (robot.x, robot.y);
+        if x < viewport.x
+            || y < viewport.y
+            || x >= viewport.x + viewport.width
+            || y >= viewport.y + viewport.height
+        {
+            continue;
+        }
+        if robot.kind == TypeCase::Collector && map[y][x] == TypeCase::Base {
+            continue;
+        }
+        displayed_map[y - viewport.y][x - viewport.x] = robot.kind.clone();
+    }
+
+    // Position of the selected robot relative to the viewport
+    let selected =
+        selected.and_then(|(x, y)| Some((x.checked_sub(viewport.x)?, y.checked_sub(viewport.y)?)));
+
+    let mut lines = Vec::new();
+    // Each character sums up a block of scale x scale cases when condensed
+    for block_y in (0..displayed_map.len()).step_by(viewport.scale) {
+        let mut line = MapLine::default();
+        for block_x in (0..viewport.width).step_by(viewport.scale) {
+            let rows = block_y..(block_y + viewport.scale).min(viewport.height);
+            let columns = block_x..(block_x + viewport.scale).min(viewport.width);
+            let highlighted =
+                selected.is_some_and(|(x, y)| rows.contains(&y) && columns.contains(&x));
+
+            let case = displayed_map[rows]
+                .iter()
+                .flat_map(|row| row[columns.clone()].iter())
+                .max_by_key(|case| display_priority(case))
+                .unwrap_or(&TypeCase::Unknown);
+
+            if viewport.condensed {
+                line.push(&condensed_symbol(case).to_string(), highlighted);
+            } else {
+                line.push(symbol(case), highlighted);
+            }
+        }
+        lines.push(line.finish());
+    }
+    Text::from(lines)
+}
+
+// Builds a line of the map, only the selected robot gets its own highlighted span
+#[derive(Default)]
+struct MapLine {
+    spans: Vec<Span<'static>>,
+    buffer: String,
+}
+
+impl MapLine {
+    fn push(&mut self, symbol: &str, highlighted: bool) {
+        if highlighted {
+            self.spans.push(Span::raw(std::mem::take(&mut self.buffer)));
+            self.spans
+                .push(Span::styled(symbol.to_string(), highlight_style()));
+        } else {
+            self.buffer.push_str(symbol);
+        }
+    }
+
+    fn finish(mut self) -> Line<'static> {
+        self.spans.push(Span::raw(self.buffer));
+        Line::from(self.spans)
+    }
+}
+
+fn symbol(case: &TypeCase) -> &'static str {
+    match case {
+        TypeCase::Void => "  ",
+        TypeCase::Wall => "🪨",
+        TypeCase::Energy => "⚡",
+        TypeCase::Ore => "💎",
+        TypeCase::Science => "🧪",
+        TypeCase::Base => "🏠",
+        TypeCase::Explorer => "🛸",
+        TypeCase::Collector => "🤖",
+        TypeCase::Unknown => "▒▒",
+    }
+}
+
+fn condensed_symbol(case: &TypeCase) -> char {
+    match case {
+        TypeCase::Void => ' ',
+        TypeCase::Wall => '#',
+        TypeCase::Energy => 'e',
+        TypeCase::Ore => 'o',
+        TypeCase::Science => 's',
+        TypeCase::Base => 'B',
+        TypeCase::Explorer => 'X',
+        TypeCase::Collector => 'C',
+        TypeCase::Unknown => '▒',
+    }
+}
+
+// Which case represents a block when zoomed out, the most interesting one wins
+fn display_priority(case: &TypeCase) -> u8 {
+    match case {
+        TypeCase::Base => 8,
+        TypeCase::Explorer => 7,
+        TypeCase::Collector => 6,
+        TypeCase::Science => 5,
+        TypeCase::Energy => 4,
+        TypeCase::Ore => 3,
+        TypeCase::Wall => 2,
+        TypeCase::Void => 1,
+        TypeCase::Unknown => 0,
+    }
+}